@@ -0,0 +1,19 @@
+# Minimal bot: keeps the paddle level with the closest ball.
+# Run with: pong --left "bot:python3 bots/follow_ball.py"
+import json
+import sys
+
+AXIS = {"LEFT": 1, "RIGHT": 1, "BOTTOM": 0, "TOP": 0}
+
+for line in sys.stdin:
+    state = json.loads(line)
+    paddle = next(p for p in state["paddles"] if p["side"] == state["side"])
+    axis = AXIS[state["side"]]
+
+    move = 0.0
+    if state["balls"]:
+        ball = min(state["balls"], key=lambda b: abs(b["position"][1 - axis] - paddle["position"][1 - axis]))
+        offset = ball["position"][axis] - paddle["position"][axis]
+        move = max(-1.0, min(1.0, offset / 20.0))
+
+    print(json.dumps({"move": move, "tick": state["tick"]}), flush=True)
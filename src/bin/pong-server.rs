@@ -0,0 +1,320 @@
+// Dedicated server: runs the match headless, the first two clients play and the server decides
+// every movement, goal and state switch; clients only send inputs and render snapshots.
+// Spectators receive the same snapshots with a delay and never control a paddle.
+// The match is advertised on the local network for clients browsing the lobby.
+// Runs the fixed-tick Simulation rather than the game plugins: snapshots describe its state.
+// It shares the ball step and the scoreboard rules with PhysicsPlugin, and its phases switch like
+// the StateMachine of StatePlugin from Start on, as the simulation tests check.
+
+// import std
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+
+// import local
+use pong::{Arena, WIN_CONDITIONS};
+use pong::protocol::{
+	Advert, InputPacket, Snapshot, WatchPacket, CONNECTION_TIMEOUT, DEFAULT_SERVER_ADDRESS, DISCOVERY_PORT,
+};
+use pong::simulation::{PaddleInput, Phase, Simulation, SIDES, TICK_RATE};
+
+const DEFAULT_SPECTATOR_DELAY: f32 = 2.0;
+const DEFAULT_NAME: &str            = "Pong";
+const ADVERT_INTERVAL: Duration     = Duration::from_secs(1);
+
+fn main() {
+	let settings = ServerSettings::from_args(std::env::args().skip(1));
+
+	let socket = UdpSocket::bind(&settings.bind).and_then(|socket| {
+		socket.set_nonblocking(true)?;
+		socket.set_broadcast(true)?;
+		Ok(socket)
+	});
+	let socket = match socket {
+		Ok(socket) => socket,
+		Err(error) => { eprintln!("Can't bind {}: {error}", settings.bind); return }
+	};
+
+	let mut app = App::new();
+
+	// Plugins: no window, one update per simulation tick
+	app.add_plugins((
+		MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / TICK_RATE))),
+		LogPlugin::default(),
+	));
+
+	// Resources
+	app.insert_resource(Server {
+			socket,
+			players: [None, None],
+			spectators: Vec::new(),
+			tick: 0,
+		})
+		.insert_resource(Match {
+			simulation: Simulation::default(),
+			collision: false,
+		})
+		.insert_resource(SpectatorFeed {
+			delay: (settings.spectator_delay * TICK_RATE as f32).round() as usize,
+			snapshots: VecDeque::new(),
+		})
+		.insert_resource(Lobby {
+			name: settings.name.clone(),
+			rules: format!("versus to {WIN_CONDITIONS}, spectators {}s behind", settings.spectator_delay),
+			target: settings.advertise.clone(),
+			timer: Timer::new(ADVERT_INTERVAL, TimerMode::Repeating),
+		})
+		.insert_resource(Arena::default());
+
+	// Systems
+	app.add_systems(Startup, log_address)
+		.add_systems(Update, (
+			receive_inputs,
+			drop_idle_clients,
+			step_match,
+			broadcast_snapshots,
+			feed_spectators,
+			advertise_match,
+		).chain());
+
+	app.run();
+}
+
+// Resources
+#[derive(Resource)] struct Server {
+	socket: UdpSocket,
+	players: [Option<Client>; 2], // indexed like the paddles of the simulation
+	spectators: Vec<Client>,
+	tick: u32,
+}
+#[derive(Resource)] struct Match {
+	simulation: Simulation,
+	collision: bool, // the ball bounced during the last tick
+}
+#[derive(Resource)] struct SpectatorFeed {
+	delay: usize, // ticks
+	snapshots: VecDeque<Snapshot>, // newest last
+}
+
+#[derive(Resource)] struct Lobby {
+	name: String,
+	rules: String,
+	target: String, // broadcast address adverts are sent to
+	timer: Timer,
+}
+
+struct ServerSettings { bind: String, spectator_delay: f32, name: String, advertise: String }
+struct Client {
+	address: SocketAddr,
+	sequence: u32,
+	input: PaddleInput,
+	last_seen: Duration,
+}
+
+impl ServerSettings {
+	// Usage: pong-server [--bind <address>] [--spectator-delay <seconds>] [--name <name>] [--advertise <address>]
+	fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+		let mut settings = ServerSettings {
+			bind: DEFAULT_SERVER_ADDRESS.into(),
+			spectator_delay: DEFAULT_SPECTATOR_DELAY,
+			name: DEFAULT_NAME.into(),
+			advertise: format!("255.255.255.255:{DISCOVERY_PORT}"),
+		};
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--bind" => match args.next() {
+					Some(address) => settings.bind = address,
+					None => eprintln!("Expected address after {arg}"),
+				},
+				"--spectator-delay" => match args.next().and_then(|value| value.parse::<f32>().ok()) {
+					Some(seconds) if seconds >= 0.0 => settings.spectator_delay = seconds,
+					_ => eprintln!("Expected seconds after {arg}"),
+				},
+				"--name" => match args.next() {
+					Some(name) => settings.name = name,
+					None => eprintln!("Expected name after {arg}"),
+				},
+				"--advertise" => match args.next() {
+					Some(address) => settings.advertise = address,
+					None => eprintln!("Expected address after {arg}"),
+				},
+				_ => eprintln!("Unknown argument: {arg}"),
+			}
+		}
+
+		settings
+	}
+}
+
+impl Server {
+	fn player_count(&self) -> u8 {
+		self.players.iter().flatten().count() as u8
+	}
+}
+
+fn log_address(
+	server: Res<Server>,
+) {
+	match server.socket.local_addr() {
+		Ok(address) => info!("SERVER: listening on {address}"),
+		Err(error)  => error!("SERVER: {error}"),
+	}
+}
+
+fn receive_inputs(
+	mut server: ResMut<Server>,
+	time: Res<Time<Real>>,
+) {
+	let mut buffer = [0; 512];
+	loop {
+		let (size, address) = match server.socket.recv_from(&mut buffer) {
+			Ok(received) => received,
+			Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+			Err(error) => { warn!("SERVER: {error}"); break }
+		};
+
+		// Spectators only keep their place in the audience
+		if WatchPacket::decode(&buffer[..size]).is_some() {
+			match server.spectators.iter_mut().find(|client| client.address == address) {
+				Some(client) => client.last_seen = time.elapsed(),
+				None => {
+					info!("SERVER: {address} is watching");
+					server.spectators.push(Client { address, sequence: 0, input: PaddleInput::default(), last_seen: time.elapsed() });
+				}
+			}
+			continue;
+		}
+
+		// Malformed packets and unknown buttons are dropped by the decoder
+		let Some(packet) = InputPacket::decode(&buffer[..size]) else { continue };
+
+		// Known player, or a free paddle for a new one
+		let index = server.players.iter().position(|client| client.as_ref().is_some_and(|client| client.address == address))
+			.or_else(|| server.players.iter().position(Option::is_none));
+		let Some(index) = index else { continue };
+
+		let client = server.players[index].get_or_insert_with(|| {
+			info!("SERVER: {address} joined as {}", SIDES[index].name());
+			Client { address, sequence: 0, input: PaddleInput::default(), last_seen: Duration::ZERO }
+		});
+
+		// Reordered packets carry stale inputs
+		if packet.sequence <= client.sequence { continue }
+		client.sequence = packet.sequence;
+		client.input = packet.input;
+		client.last_seen = time.elapsed();
+	}
+}
+
+fn drop_idle_clients(
+	mut server: ResMut<Server>,
+	mut game: ResMut<Match>,
+	time: Res<Time<Real>>,
+) {
+	let timeout = Duration::from_secs_f32(CONNECTION_TIMEOUT);
+	let mut is_dropped = false;
+
+	for (index, slot) in server.players.iter_mut().enumerate() {
+		let Some(client) = slot else { continue };
+		if time.elapsed() - client.last_seen < timeout { continue }
+
+		info!("SERVER: {} left, {} paddle is free", client.address, SIDES[index].name());
+		*slot = None;
+		is_dropped = true;
+	}
+
+	// The remaining player waits for a new opponent from scratch
+	if is_dropped { game.simulation = Simulation::default() }
+
+	server.spectators.retain(|client| {
+		let is_idle = time.elapsed() - client.last_seen >= timeout;
+		if is_idle { info!("SERVER: {} stopped watching", client.address) }
+		!is_idle
+	});
+}
+
+fn step_match(
+	mut server: ResMut<Server>,
+	mut game: ResMut<Match>,
+	arena: Res<Arena>,
+) {
+	server.tick += 1;
+	game.collision = false;
+
+	if server.player_count() < 2 { return }
+
+	let inputs = server.players.each_ref().map(|client| client.as_ref().map_or(PaddleInput::default(), |client| client.input));
+	let conceded = game.simulation.conceded;
+	let events = game.simulation.step(inputs, &arena);
+	game.collision = events.collision;
+
+	if events.goal.is_some() {
+		info!("SCORE: {} {}", game.simulation.conceded[1], game.simulation.conceded[0]);
+	}
+	if game.simulation.phase == Phase::GameOver && conceded != game.simulation.conceded {
+		info!("SERVER: game over");
+	}
+}
+
+fn broadcast_snapshots(
+	server: Res<Server>,
+	game: Res<Match>,
+) {
+	let players = server.player_count();
+
+	for (index, client) in server.players.iter().enumerate() {
+		let Some(client) = client else { continue };
+
+		let snapshot = Snapshot::new(server.tick, &game.simulation, players, Some(SIDES[index]), game.collision);
+		if let Err(error) = server.socket.send_to(&snapshot.encode(), client.address) {
+			warn!("SERVER: {error}");
+		}
+	}
+}
+
+fn feed_spectators(
+	server: Res<Server>,
+	game: Res<Match>,
+	mut feed: ResMut<SpectatorFeed>,
+) {
+	let snapshot = Snapshot::new(server.tick, &game.simulation, server.player_count(), None, game.collision);
+	feed.snapshots.push_back(snapshot);
+	while feed.snapshots.len() > feed.delay + 1 { feed.snapshots.pop_front(); }
+
+	// Oldest kept snapshot, the full delay once the server has run long enough
+	let Some(delayed) = feed.snapshots.front() else { return };
+	let packet = delayed.encode();
+	for client in &server.spectators {
+		if let Err(error) = server.socket.send_to(&packet, client.address) {
+			warn!("SERVER: {error}");
+		}
+	}
+}
+
+fn advertise_match(
+	server: Res<Server>,
+	mut lobby: ResMut<Lobby>,
+	time: Res<Time<Real>>,
+) {
+	lobby.timer.tick(time.delta());
+	if !lobby.timer.just_finished() { return }
+
+	let Ok(address) = server.socket.local_addr() else { return };
+	let advert = Advert {
+		port: address.port(),
+		name: lobby.name.clone(),
+		rules: lobby.rules.clone(),
+		players: server.player_count(),
+		peer_side: None,
+	};
+	if let Err(error) = server.socket.send_to(&advert.encode(), lobby.target.as_str()) {
+		warn!("LOBBY: {error}");
+	}
+}
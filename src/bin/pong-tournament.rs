@@ -0,0 +1,336 @@
+// Tournament: every pair of players meets in headless versus matches run at full speed on the
+// simulation of networked play, so collisions and scoring are the same as in the game.
+// Reports win rates, average rally lengths and Elo ratings, optionally to a CSV or JSON file.
+
+// import std
+use std::fmt::Write as _;
+use std::fs;
+use std::time::{Duration, Instant};
+
+// import custom
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+
+// import local
+use pong::Arena;
+use pong::bot::{BotLink, BotState};
+use pong::env::{ObservationConfig, PaddleView};
+use pong::neural::NeuralNetwork;
+use pong::simulation::{HeadlessAi, PaddleInput, Phase, Simulation, TICK_RATE};
+
+const DEFAULT_ROUNDS: u32           = 10;
+const DEFAULT_MAX_TICKS: u32        = 10 * 60 * TICK_RATE as u32;
+const DEFAULT_BOT_TIMEOUT: Duration = Duration::from_millis(100); // nothing is rendered, bots can think longer
+
+const STARTING_ELO: f64 = 1000.0;
+const ELO_K: f64        = 32.0;
+
+fn main() {
+	let settings = TournamentSettings::from_args(std::env::args().skip(1));
+
+	let mut players = Vec::new();
+	for spec in &settings.players {
+		match Player::open(spec, settings.bot_timeout) {
+			Ok(player) => players.push(player),
+			Err(error) => { eprintln!("Can't start {spec}: {error}"); return }
+		}
+	}
+	if players.len() < 2 {
+		eprintln!("Usage: pong-tournament [--rounds <n>] [--seed <n>] [--max-ticks <n>] [--bot-timeout <ms>] [--report <file.csv|file.json>] <player> <player>...");
+		eprintln!("Players: ai, offensive, idle, nn:<easy|medium|hard|file.ron>, bot:<command>, tcp:<address>");
+		return;
+	}
+
+	let arena = Arena::default();
+	let mut rng = StdRng::seed_from_u64(settings.seed);
+	let mut matches = Vec::new();
+	let started = Instant::now();
+
+	for round in 0..settings.rounds {
+		for first in 0..players.len() {
+			for second in first + 1..players.len() {
+				// Sides swap every round
+				let (left, right) = if round % 2 == 0 { (first, second) } else { (second, first) };
+				let Ok([left_player, right_player]) = players.get_disjoint_mut([left, right]) else { continue };
+
+				let record = play_match(round, left_player, right_player, &arena, &mut rng, settings.max_ticks);
+				update_ratings(left_player, right_player, &record);
+				matches.push(record);
+			}
+		}
+	}
+
+	let elapsed = started.elapsed().as_secs_f64();
+	let ticks: u64 = matches.iter().map(|record| u64::from(record.ticks)).sum();
+	println!("{} matches, {ticks} ticks in {elapsed:.2}s ({:.0} ticks/s)", matches.len(), ticks as f64 / elapsed.max(f64::EPSILON));
+
+	let mut standings: Vec<Standing> = players.iter().map(Player::standing).collect();
+	standings.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+	print!("{}", standings_table(&standings));
+
+	if let Some(path) = &settings.report {
+		let report = Report { standings, matches };
+		let written = match path.ends_with(".json") {
+			true  => serde_json::to_string_pretty(&report).map_err(std::io::Error::other).and_then(|json| fs::write(path, json)),
+			false => fs::write(path, report.to_csv()),
+		};
+		match written {
+			Ok(()) => println!("Report written to {path}"),
+			Err(error) => eprintln!("Can't write {path}: {error}"),
+		}
+	}
+}
+
+struct TournamentSettings {
+	players: Vec<String>,
+	rounds: u32,
+	seed: u64,
+	max_ticks: u32,
+	bot_timeout: Duration,
+	report: Option<String>,
+}
+
+enum Strategy {
+	Ai(HeadlessAi),        // plans like the AI of the game
+	Offensive(HeadlessAi), // and aims returns away from the opponent
+	Idle,                  // never moves, a baseline
+	Neural(NeuralNetwork),
+	Bot(BotLink, HeadlessAi), // the AI takes over while the bot is late
+}
+
+struct Player {
+	name: String,
+	strategy: Strategy,
+	elo: f64,
+	matches: u32,
+	wins: u32,
+	draws: u32,
+	rallies: u32,
+	hits: u32,
+}
+
+#[derive(Serialize)] struct Standing {
+	name: String,
+	matches: u32,
+	wins: u32,
+	draws: u32,
+	losses: u32,
+	win_rate: f64,
+	average_rally: f64, // paddle touches per point
+	elo: f64,
+}
+
+#[derive(Serialize)] struct MatchRecord {
+	round: u32,
+	left: String,
+	right: String,
+	left_score: u32,
+	right_score: u32,
+	winner: Option<String>, // None for matches cut at the tick limit with an even score
+	ticks: u32,
+	rallies: u32,
+	hits: u32,
+}
+
+#[derive(Serialize)] struct Report {
+	standings: Vec<Standing>,
+	matches: Vec<MatchRecord>,
+}
+
+impl TournamentSettings {
+	fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+		let mut settings = TournamentSettings {
+			players: Vec::new(),
+			rounds: DEFAULT_ROUNDS,
+			seed: 0,
+			max_ticks: DEFAULT_MAX_TICKS,
+			bot_timeout: DEFAULT_BOT_TIMEOUT,
+			report: None,
+		};
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--rounds" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(rounds) => settings.rounds = rounds,
+					None => eprintln!("Expected number after {arg}"),
+				},
+				"--seed" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(seed) => settings.seed = seed,
+					None => eprintln!("Expected number after {arg}"),
+				},
+				"--max-ticks" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(ticks) if ticks > 0 => settings.max_ticks = ticks,
+					_ => eprintln!("Expected number after {arg}"),
+				},
+				"--bot-timeout" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(millis) => settings.bot_timeout = Duration::from_millis(millis),
+					None => eprintln!("Expected milliseconds after {arg}"),
+				},
+				"--report" => match args.next() {
+					Some(path) => settings.report = Some(path),
+					None => eprintln!("Expected path after {arg}"),
+				},
+				_ if arg.starts_with("--") => eprintln!("Unknown argument: {arg}"),
+				_ => settings.players.push(arg),
+			}
+		}
+
+		settings
+	}
+}
+
+impl Player {
+	// "ai", "offensive", "idle", "nn:<network>", "bot:<command>" or "tcp:<address>"
+	fn open(spec: &str, bot_timeout: Duration) -> std::io::Result<Self> {
+		let strategy = match spec {
+			"ai"        => Strategy::Ai(HeadlessAi::default()),
+			"offensive" => Strategy::Offensive(HeadlessAi::new(Duration::ZERO, 0.0, true)),
+			"idle"      => Strategy::Idle,
+			_ if spec.starts_with("nn:") => Strategy::Neural(NeuralNetwork::load(&spec[3..]).map_err(std::io::Error::other)?),
+			_ => match spec.strip_prefix("bot:") {
+				Some(command) => Strategy::Bot(BotLink::open(command, bot_timeout)?, HeadlessAi::default()),
+				None if spec.starts_with("tcp:") => Strategy::Bot(BotLink::open(spec, bot_timeout)?, HeadlessAi::default()),
+				None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "unknown player")),
+			},
+		};
+
+		Ok(Self { name: spec.into(), strategy, elo: STARTING_ELO, matches: 0, wins: 0, draws: 0, rallies: 0, hits: 0 })
+	}
+
+	// Bots that don't answer in time are replaced by the plan of the built-in AI for that tick, as in the game
+	fn input(&mut self, simulation: &Simulation, index: usize, arena: &Arena, rng: &mut StdRng) -> PaddleInput {
+		match &mut self.strategy {
+			Strategy::Ai(ai) | Strategy::Offensive(ai) => ai.input(simulation, index, arena, rng),
+			Strategy::Idle => PaddleInput::default(),
+			Strategy::Neural(network) => {
+				let observation = ObservationConfig::default().observe(&PaddleView::from_simulation(simulation, index), arena);
+				PaddleInput::from_direction(network.direction(&observation))
+			}
+			Strategy::Bot(link, ai) => {
+				// the plan follows the ball even while the bot answers, to be ready when it doesn't
+				let fallback = ai.input(simulation, index, arena, rng);
+				match link.exchange(&BotState::from_simulation(simulation, index, arena)) {
+					Some(direction) => PaddleInput::from_direction(direction),
+					None => fallback,
+				}
+			}
+		}
+	}
+
+	fn standing(&self) -> Standing {
+		Standing {
+			name: self.name.clone(),
+			matches: self.matches,
+			wins: self.wins,
+			draws: self.draws,
+			losses: self.matches - self.wins - self.draws,
+			win_rate: f64::from(self.wins) / f64::from(self.matches.max(1)),
+			average_rally: f64::from(self.hits) / f64::from(self.rallies.max(1)),
+			elo: self.elo,
+		}
+	}
+}
+
+impl Report {
+	// Standings only, matches need the JSON report
+	fn to_csv(&self) -> String {
+		let mut csv = String::from("name,matches,wins,draws,losses,win_rate,average_rally,elo\n");
+		for standing in &self.standings {
+			let _ = writeln!(
+				csv, "{},{},{},{},{},{:.4},{:.2},{:.1}",
+				csv_field(&standing.name), standing.matches, standing.wins, standing.draws, standing.losses,
+				standing.win_rate, standing.average_rally, standing.elo,
+			);
+		}
+		csv
+	}
+}
+
+fn csv_field(text: &str) -> String {
+	match text.contains([',', '"', '\n']) {
+		true  => format!("\"{}\"", text.replace('"', "\"\"")),
+		false => text.into(),
+	}
+}
+
+fn play_match(
+	round: u32,
+	left: &mut Player,
+	right: &mut Player,
+	arena: &Arena,
+	rng: &mut StdRng,
+	max_ticks: u32,
+) -> MatchRecord {
+	let mut simulation = Simulation::default();
+	let mut last_hit = None;
+	let (mut rallies, mut hits) = (0, 0);
+
+	while simulation.phase != Phase::GameOver && simulation.tick < max_ticks {
+		simulation.prepare_serve(rng, true);
+		let inputs = [left.input(&simulation, 0, arena, rng), right.input(&simulation, 1, arena, rng)];
+		let events = simulation.step(inputs, arena);
+
+		// A touch lasts a few ticks, count it once
+		if events.hit.is_some() && events.hit != last_hit { hits += 1 }
+		if events.hit.is_some() { last_hit = events.hit }
+		if events.goal.is_some() {
+			rallies += 1;
+			last_hit = None;
+		}
+	}
+
+	let (left_score, right_score) = (simulation.conceded[1], simulation.conceded[0]);
+	let winner = match left_score.cmp(&right_score) {
+		std::cmp::Ordering::Greater => Some(left.name.clone()),
+		std::cmp::Ordering::Less    => Some(right.name.clone()),
+		std::cmp::Ordering::Equal   => None,
+	};
+
+	MatchRecord {
+		round,
+		left: left.name.clone(),
+		right: right.name.clone(),
+		left_score,
+		right_score,
+		winner,
+		ticks: simulation.tick,
+		rallies,
+		hits,
+	}
+}
+
+fn update_ratings(
+	left: &mut Player,
+	right: &mut Player,
+	record: &MatchRecord,
+) {
+	let score = match record.left_score.cmp(&record.right_score) {
+		std::cmp::Ordering::Greater => 1.0,
+		std::cmp::Ordering::Less    => 0.0,
+		std::cmp::Ordering::Equal   => 0.5,
+	};
+	let expected = 1.0 / (1.0 + 10f64.powf((right.elo - left.elo) / 400.0));
+	left.elo += ELO_K * (score - expected);
+	right.elo -= ELO_K * (score - expected);
+
+	for (player, is_winner) in [(left, score == 1.0), (right, score == 0.0)] {
+		player.matches += 1;
+		player.rallies += record.rallies;
+		player.hits += record.hits;
+		if is_winner { player.wins += 1 }
+		if score == 0.5 { player.draws += 1 }
+	}
+}
+
+fn standings_table(standings: &[Standing]) -> String {
+	let width = standings.iter().map(|standing| standing.name.len()).max().unwrap_or(0).max(4);
+	let mut table = format!("{:<width$}  {:>7}  {:>4}  {:>5}  {:>6}  {:>8}  {:>5}  {:>7}\n", "name", "matches", "wins", "draws", "losses", "win rate", "rally", "elo");
+	for standing in standings {
+		let _ = writeln!(
+			table, "{:<width$}  {:>7}  {:>4}  {:>5}  {:>6}  {:>7.1}%  {:>5.1}  {:>7.1}",
+			standing.name, standing.matches, standing.wins, standing.draws, standing.losses,
+			standing.win_rate * 100.0, standing.average_rally, standing.elo,
+		);
+	}
+	table
+}
@@ -0,0 +1,202 @@
+// Offline trainer of learned AIs: a genetic algorithm evolves the weights of a neural network
+// playing headless matches against the offensive AI of the training environment.
+// The best network of every generation is saved, and checkpoints keep weaker ones to ship.
+
+// import std
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+// import custom
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// import local
+use pong::env::{Action, Env, EnvConfig, ObservationConfig};
+use pong::neural::NeuralNetwork;
+
+const DEFAULT_GENERATIONS: u32 = 100;
+const DEFAULT_POPULATION: usize = 64;
+const DEFAULT_EPISODES: u32     = 4;
+const DEFAULT_HIDDEN: usize     = 8;
+const DEFAULT_OUTPUT: &str      = "neural.ron";
+
+const ELITE: usize            = 4;    // best genomes copied unchanged into the next generation
+const SELECTION_SIZE: usize   = 3;    // genomes compared to pick each parent
+const MUTATION_RATE: f64      = 0.1;  // chance of every parameter to mutate
+const MUTATION_STRENGTH: f32  = 0.3;  // standard deviation of mutations
+const MAX_EPISODE_TICKS: u32  = 60 * 60 * 2;
+
+fn main() {
+	let settings = TrainerSettings::from_args(std::env::args().skip(1));
+
+	let config = EnvConfig { max_ticks: MAX_EPISODE_TICKS, ..EnvConfig::default() };
+	let sizes = [ObservationConfig::default().observation_size(), settings.hidden, 1];
+	let mut rng = StdRng::seed_from_u64(settings.seed);
+	let mut population: Vec<NeuralNetwork> = (0..settings.population).map(|_| NeuralNetwork::random(&sizes, &mut rng)).collect();
+	let started = Instant::now();
+
+	for generation in 1..=settings.generations {
+		// Every genome meets the same serves within a generation
+		let seeds: Vec<u64> = (0..settings.episodes).map(|_| rng.gen()).collect();
+		let fitness = evaluate(&population, &config, &seeds);
+
+		let mut ranking: Vec<usize> = (0..population.len()).collect();
+		ranking.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+		let best = &population[ranking[0]];
+		let mean = fitness.iter().sum::<f32>() / fitness.len() as f32;
+		println!("generation {generation:>4}: best {:>7.2}, mean {mean:>7.2} ({:.1}s)", fitness[ranking[0]], started.elapsed().as_secs_f32());
+
+		save(best, &settings.output);
+		if settings.checkpoint_every > 0 && generation % settings.checkpoint_every == 0 {
+			save(best, &checkpoint_path(&settings.output, generation));
+		}
+
+		population = next_generation(&population, &ranking, &fitness, &mut rng);
+	}
+}
+
+struct TrainerSettings {
+	generations: u32,
+	population: usize,
+	episodes: u32,
+	hidden: usize,
+	seed: u64,
+	output: PathBuf,
+	checkpoint_every: u32,
+}
+
+impl TrainerSettings {
+	// Usage: pong-trainer [--generations <n>] [--population <n>] [--episodes <n>] [--hidden <n>] [--seed <n>]
+	//                     [--output <file.ron>] [--checkpoint-every <n>]
+	fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+		let mut settings = TrainerSettings {
+			generations: DEFAULT_GENERATIONS,
+			population: DEFAULT_POPULATION,
+			episodes: DEFAULT_EPISODES,
+			hidden: DEFAULT_HIDDEN,
+			seed: 0,
+			output: DEFAULT_OUTPUT.into(),
+			checkpoint_every: 0,
+		};
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--generations" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(generations) => settings.generations = generations,
+					None => eprintln!("Expected number after {arg}"),
+				},
+				"--population" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(population) if population > ELITE => settings.population = population,
+					_ => eprintln!("Expected number above {ELITE} after {arg}"),
+				},
+				"--episodes" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(episodes) if episodes > 0 => settings.episodes = episodes,
+					_ => eprintln!("Expected number after {arg}"),
+				},
+				"--hidden" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(hidden) if hidden > 0 => settings.hidden = hidden,
+					_ => eprintln!("Expected number after {arg}"),
+				},
+				"--seed" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(seed) => settings.seed = seed,
+					None => eprintln!("Expected number after {arg}"),
+				},
+				"--output" => match args.next() {
+					Some(path) => settings.output = path.into(),
+					None => eprintln!("Expected path after {arg}"),
+				},
+				"--checkpoint-every" => match args.next().and_then(|value| value.parse().ok()) {
+					Some(generations) => settings.checkpoint_every = generations,
+					None => eprintln!("Expected number after {arg}"),
+				},
+				_ => eprintln!("Unknown argument: {arg}"),
+			}
+		}
+
+		settings
+	}
+}
+
+// Total reward over the episodes, genomes are spread over the cores
+fn evaluate(
+	population: &[NeuralNetwork],
+	config: &EnvConfig,
+	seeds: &[u64],
+) -> Vec<f32> {
+	let threads = thread::available_parallelism().map_or(1, usize::from);
+	let chunk_size = population.len().div_ceil(threads);
+
+	thread::scope(|scope| {
+		let workers: Vec<_> = population.chunks(chunk_size)
+			.map(|chunk| scope.spawn(move || chunk.iter().map(|network| play(network, config, seeds)).collect::<Vec<f32>>()))
+			.collect();
+		workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+	})
+}
+
+fn play(
+	network: &NeuralNetwork,
+	config: &EnvConfig,
+	seeds: &[u64],
+) -> f32 {
+	let mut env = Env::new(config.clone());
+	let mut total = 0.0;
+	for seed in seeds {
+		let mut observation = env.reset(*seed);
+		loop {
+			let result = env.step(Action::from_direction(network.direction(&observation)));
+			total += result.reward;
+			observation = result.observation;
+			if result.done { break }
+		}
+	}
+	total
+}
+
+fn next_generation(
+	population: &[NeuralNetwork],
+	ranking: &[usize],
+	fitness: &[f32],
+	rng: &mut StdRng,
+) -> Vec<NeuralNetwork> {
+	let mut next: Vec<NeuralNetwork> = ranking.iter().take(ELITE).map(|index| population[*index].clone()).collect();
+
+	while next.len() < population.len() {
+		let first  = &population[select(fitness, rng)];
+		let second = &population[select(fitness, rng)];
+
+		// Uniform crossover, then gaussian mutation
+		let mut child = first.clone();
+		for (parameter, other) in child.parameters_mut().zip(second.parameters()) {
+			if rng.gen_bool(0.5) { *parameter = *other }
+			if rng.gen_bool(MUTATION_RATE) { *parameter += gaussian(rng) * MUTATION_STRENGTH }
+		}
+		next.push(child);
+	}
+	next
+}
+
+// Tournament selection
+fn select(fitness: &[f32], rng: &mut StdRng) -> usize {
+	(0..SELECTION_SIZE)
+		.map(|_| rng.gen_range(0..fitness.len()))
+		.max_by(|a, b| fitness[*a].total_cmp(&fitness[*b]))
+		.unwrap_or(0)
+}
+
+// Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f32 {
+	let (u, v): (f32, f32) = (rng.gen_range(f32::EPSILON..1.0), rng.gen());
+	(-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+fn checkpoint_path(output: &Path, generation: u32) -> PathBuf {
+	let stem = output.file_stem().and_then(|stem| stem.to_str()).unwrap_or("neural");
+	output.with_file_name(format!("{stem}-{generation:04}.ron"))
+}
+
+fn save(network: &NeuralNetwork, path: &Path) {
+	if let Err(error) = network.save(path) {
+		eprintln!("Can't write {}: {error}", path.display());
+	}
+}
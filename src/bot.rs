@@ -0,0 +1,314 @@
+// External bots: a program controls a paddle by reading one JSON state per line and answering
+// with one JSON command per line, either over its stdin/stdout or over a TCP connection.
+//
+// State:   {"tick":7,"state":"Active","side":"LEFT","balls":[{"position":[0,0],"velocity":[283,283]}],
+//           "paddles":[{"side":"LEFT","position":[-300,0],"velocity":[0,0],"conceded":0}, ...]}
+// Command: {"move":1.0} moves the paddle up/right at full speed, -1.0 down/left, 0.0 stops;
+//          an optional "tick" drops answers to older states.
+
+// import std
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// import bevy
+use bevy::prelude::*;
+
+// import custom
+use serde::{Deserialize, Serialize};
+
+// import crate
+use crate::Arena;
+use crate::simulation::{Phase, Simulation, SIDES};
+
+pub const DEFAULT_BOT_TIMEOUT: Duration = Duration::from_millis(10);
+
+#[derive(Serialize, Debug, Clone)] pub struct BotState {
+	pub tick: u64,
+	pub state: String,       // GameplayState of the game
+	pub side: &'static str,  // paddle controlled by the bot
+	pub balls: Vec<BallState>,
+	pub paddles: Vec<PaddleState>,
+}
+#[derive(Serialize, Debug, Clone)] pub struct BallState {
+	pub position: [f32; 2],
+	pub velocity: [f32; 2],
+}
+#[derive(Serialize, Debug, Clone)] pub struct PaddleState {
+	pub side: &'static str,
+	pub position: [f32; 2],
+	pub velocity: [f32; 2],
+	pub conceded: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)] pub struct BotCommand {
+	#[serde(rename = "move")]
+	pub direction: f32,
+	#[serde(default)]
+	pub tick: Option<u64>,
+}
+
+impl BotState {
+	// State of a headless match seen from the paddle at index, named like the states of the game
+	pub fn from_simulation(simulation: &Simulation, index: usize, arena: &Arena) -> Self {
+		let state = match simulation.phase {
+			Phase::Start(_)   => "Start",
+			Phase::Active     => "Active",
+			Phase::NextSet(_) => "NextSet",
+			Phase::GameOver   => "GameOver",
+		};
+
+		Self {
+			tick: u64::from(simulation.tick),
+			state: state.into(),
+			side: SIDES[index].name(),
+			balls: vec![BallState {
+				position: simulation.ball_position.to_array(),
+				velocity: simulation.ball_velocity.to_array(),
+			}],
+			paddles: SIDES.iter().enumerate()
+				.map(|(paddle, side)| PaddleState {
+					side: side.name(),
+					position: simulation.paddle_translation(paddle, arena).to_array(),
+					velocity: (side.axis() * simulation.paddle_velocities[paddle]).to_array(),
+					conceded: simulation.conceded[paddle],
+				})
+				.collect(),
+		}
+	}
+}
+
+// Connection to one bot program
+#[derive(Component)] pub struct BotLink {
+	name: String,
+	writer: Box<dyn Write + Send + Sync>,
+	lines: Mutex<Receiver<String>>,
+	timeout: Duration,
+	process: Option<Child>,
+	is_alive: bool,
+	answer: Option<f32>,      // last move of the bot
+	tick: u64,                // tick of the last state sent
+	waiting: Option<Instant>, // when the oldest unanswered state was sent
+}
+
+impl BotLink {
+	// "tcp:<address>" connects to a listening bot, anything else is a command line to run,
+	// quotes keep arguments with spaces together
+	pub fn open(spec: &str, timeout: Duration) -> io::Result<Self> {
+		let (writer, reader, process): (Box<dyn Write + Send + Sync>, Box<dyn io::Read + Send>, _) =
+			match spec.strip_prefix("tcp:") {
+				Some(address) => {
+					let stream = TcpStream::connect(address)?;
+					stream.set_nodelay(true)?;
+					(Box::new(stream.try_clone()?), Box::new(stream), None)
+				}
+				None => {
+					let mut parts = split_command(spec).into_iter();
+					let program = parts.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+					let mut child = Command::new(program)
+						.args(parts)
+						.stdin(Stdio::piped())
+						.stdout(Stdio::piped())
+						.spawn()?;
+					let stdin = child.stdin.take().ok_or_else(|| io::Error::other("bot stdin"))?;
+					let stdout = child.stdout.take().ok_or_else(|| io::Error::other("bot stdout"))?;
+					(Box::new(stdin), Box::new(stdout), Some(child))
+				}
+			};
+
+		// Blocking reads stay on their own thread so the game only waits up to the timeout
+		let (sender, lines) = mpsc::channel();
+		thread::spawn(move || {
+			for line in BufReader::new(reader).lines() {
+				let Ok(line) = line else { break };
+				if sender.send(line).is_err() { break }
+			}
+		});
+
+		Ok(Self {
+			name: spec.to_string(),
+			writer,
+			lines: Mutex::new(lines),
+			timeout,
+			process,
+			is_alive: true,
+			answer: None,
+			tick: 0,
+			waiting: None,
+		})
+	}
+
+	// Write the state without waiting for the answer
+	pub fn send(&mut self, state: &BotState) {
+		if !self.is_alive { return }
+
+		let sent = serde_json::to_string(state)
+			.map_err(io::Error::other)
+			.and_then(|json| writeln!(self.writer, "{json}"))
+			.and_then(|_| self.writer.flush());
+		if let Err(error) = sent {
+			warn!("BOT: {} disconnected: {error}", self.name);
+			self.is_alive = false;
+			return;
+		}
+
+		self.tick = state.tick;
+		self.waiting.get_or_insert_with(Instant::now);
+	}
+
+	// Last answer of the bot without waiting, None once it is late by more than the timeout
+	pub fn poll(&mut self) -> Option<f32> {
+		if !self.is_alive { return None }
+
+		loop {
+			let line = match self.lines.get_mut().ok()?.try_recv() {
+				Ok(line) => line,
+				Err(TryRecvError::Empty) => break,
+				Err(TryRecvError::Disconnected) => {
+					warn!("BOT: {} closed its output", self.name);
+					self.is_alive = false;
+					return None;
+				}
+			};
+			if let Some(direction) = self.parse(&line) {
+				self.answer = Some(direction);
+				self.waiting = None;
+			}
+		}
+
+		match self.waiting {
+			Some(sent) if sent.elapsed() > self.timeout => None,
+			_ => self.answer,
+		}
+	}
+
+	// Send the state and wait for the answer to it, None on timeout or a broken bot
+	pub fn exchange(&mut self, state: &BotState) -> Option<f32> {
+		self.send(state);
+		if !self.is_alive { return None }
+
+		let deadline = Instant::now() + self.timeout;
+		loop {
+			let received = self.lines.get_mut().ok()?.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+			let line = match received {
+				Ok(line) => line,
+				Err(RecvTimeoutError::Timeout) => return None,
+				Err(RecvTimeoutError::Disconnected) => {
+					warn!("BOT: {} closed its output", self.name);
+					self.is_alive = false;
+					return None;
+				}
+			};
+			if let Some(direction) = self.parse(&line) {
+				self.answer = Some(direction);
+				self.waiting = None;
+				return Some(direction);
+			}
+		}
+	}
+
+	// Move of one answer line, None for answers to older states and broken lines
+	fn parse(&self, line: &str) -> Option<f32> {
+		match serde_json::from_str::<BotCommand>(line) {
+			Ok(command) if command.tick.is_some_and(|tick| tick < self.tick) => None,
+			Ok(command) if !command.direction.is_finite() => {
+				warn!("BOT: {}: move is not a number", self.name);
+				None
+			}
+			Ok(command) => Some(command.direction.clamp(-1.0, 1.0)),
+			Err(error) => {
+				warn!("BOT: {}: {error}", self.name);
+				None
+			}
+		}
+	}
+}
+
+impl Drop for BotLink {
+	fn drop(&mut self) {
+		if let Some(process) = &mut self.process {
+			let _ = process.kill();
+			let _ = process.wait();
+		}
+	}
+}
+
+// Split a command line on spaces, text in single or double quotes stays one argument
+fn split_command(command: &str) -> Vec<String> {
+	let mut parts = Vec::new();
+	let mut part: Option<String> = None;
+	let mut quote = None;
+
+	for character in command.chars() {
+		match (quote, character) {
+			(Some(open), _) if character == open => quote = None,
+			(Some(_), _) => part.get_or_insert_with(String::new).push(character),
+			(None, '"' | '\'') => {
+				quote = Some(character);
+				part.get_or_insert_with(String::new);
+			}
+			(None, _) if character.is_whitespace() => parts.extend(part.take()),
+			(None, _) => part.get_or_insert_with(String::new).push(character),
+		}
+	}
+	parts.extend(part);
+	parts
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::TcpListener;
+
+	fn state(tick: u64) -> BotState {
+		BotState { tick, state: "Active".into(), side: "LEFT", balls: vec![], paddles: vec![] }
+	}
+
+	// Poll until an answer shows up, the bot runs on another thread
+	fn answer(link: &mut BotLink) -> Option<f32> {
+		let deadline = Instant::now() + Duration::from_secs(1);
+		while Instant::now() < deadline {
+			if let Some(direction) = link.poll() { return Some(direction) }
+			thread::sleep(Duration::from_millis(1));
+		}
+		None
+	}
+
+	#[test]
+	fn quoted_arguments_stay_together() {
+		assert_eq!(split_command("python3 bots/follow_ball.py"), ["python3", "bots/follow_ball.py"]);
+		assert_eq!(split_command("  \"/my bots/run\" --name 'Big Bot' ''"), ["/my bots/run", "--name", "Big Bot", ""]);
+		assert!(split_command("   ").is_empty());
+	}
+
+	#[test]
+	fn poll_keeps_the_last_answer_until_it_is_late() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut link = BotLink::open(&format!("tcp:{}", listener.local_addr().unwrap()), Duration::from_millis(50)).unwrap();
+		let (stream, _) = listener.accept().unwrap();
+		let mut bot = (BufReader::new(stream.try_clone().unwrap()), stream);
+		let mut line = String::new();
+
+		// no answer yet, the game doesn't wait for it
+		link.send(&state(1));
+		let start = Instant::now();
+		assert_eq!(link.poll(), None);
+		assert!(start.elapsed() < Duration::from_millis(50));
+
+		bot.0.read_line(&mut line).unwrap();
+		writeln!(bot.1, "{{\"move\":0.5,\"tick\":1}}").unwrap();
+		assert_eq!(answer(&mut link), Some(0.5));
+
+		// answers to older states are dropped, the last answer holds until the timeout
+		link.send(&state(2));
+		writeln!(bot.1, "{{\"move\":-1.0,\"tick\":1}}").unwrap();
+		thread::sleep(Duration::from_millis(10));
+		assert_eq!(link.poll(), Some(0.5));
+		thread::sleep(Duration::from_millis(60));
+		assert_eq!(link.poll(), None);
+	}
+}
@@ -0,0 +1,474 @@
+// Gym style environment over the headless simulation, for training agents.
+// The agent plays the left paddle against the offensive AI, planning like the one of the game.
+// Observations are made from the view of a paddle, so agents trained on the left play any side.
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+
+// import custom
+use rand::{rngs::StdRng, SeedableRng};
+
+// import crate
+use crate::{Arena, Side, BALL_STARTING_SPEED, PADDLE_SIZE, PLAYER_MAX_SPEED};
+use crate::simulation::{HeadlessAi, PaddleInput, Phase, Simulation, SIDES};
+
+const AGENT: usize    = 0;
+const OPPONENT: usize = 1;
+
+// Skill of the opponent, the starting level of the adaptive AI
+const OPPONENT_REACTION_DELAY: Duration = Duration::from_millis(150);
+const OPPONENT_AIM_ERROR: f32           = PADDLE_SIZE.y * 0.3;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)] pub enum Action { #[default] Stay, Up, Down }
+
+// Values in the observation vector, in this order
+#[derive(Debug, Clone, Copy)] pub struct ObservationConfig {
+	pub ball_velocity: bool,
+	pub paddle_velocity: bool,
+	pub opponent: bool,  // position of the opposing paddle
+	pub normalize: bool, // positions and velocities roughly within -1..1
+}
+
+// Reward for every event of a step
+#[derive(Debug, Clone, Copy)] pub struct RewardConfig {
+	pub hit: f32,     // agent touched the ball
+	pub score: f32,   // opponent conceded
+	pub concede: f32, // agent conceded
+}
+
+#[derive(Debug, Clone)] pub struct EnvConfig {
+	pub observation: ObservationConfig,
+	pub reward: RewardConfig,
+	pub max_ticks: u32,     // episodes are cut after this many ticks
+	pub skip_delays: bool,  // serve without waiting like the game does between sets
+	pub arena: Arena,
+	pub opponent: HeadlessAi, // built-in AI the agent plays against
+}
+
+// Game seen by one paddle, turned so its wall is on the left and it moves up and down
+#[derive(Debug, Clone, Copy, Default)] pub struct PaddleView {
+	pub side: Option<Side>,
+	pub ball_position: Vec2,
+	pub ball_velocity: Vec2,
+	pub paddle_position: f32, // along the side axis, like the opponent
+	pub paddle_velocity: f32,
+	pub opponent_position: f32,
+}
+
+#[derive(Debug, Clone)] pub struct StepResult {
+	pub observation: Vec<f32>,
+	pub reward: f32,
+	pub done: bool,
+}
+
+pub struct Env {
+	config: EnvConfig,
+	simulation: Simulation,
+	opponent: HeadlessAi,
+	rng: StdRng,
+}
+
+// Several environments stepped together, finished ones start a new episode right away
+pub struct BatchEnv {
+	envs: Vec<Env>,
+	episodes: Vec<u64>,
+	seeds: Vec<u64>,
+}
+
+impl Action {
+	pub const ALL: [Action; 3] = [Action::Stay, Action::Up, Action::Down];
+
+	pub fn from_index(index: usize) -> Option<Self> {
+		Self::ALL.get(index).copied()
+	}
+
+	pub fn from_direction(direction: f32) -> Self {
+		let input = PaddleInput::from_direction(direction);
+		match input.direction() {
+			1.0  => Action::Up,
+			-1.0 => Action::Down,
+			_    => Action::Stay,
+		}
+	}
+
+	fn input(self) -> PaddleInput {
+		PaddleInput::new(self == Action::Up, self == Action::Down, false)
+	}
+}
+
+impl Default for ObservationConfig {
+	fn default() -> Self {
+		Self { ball_velocity: true, paddle_velocity: true, opponent: true, normalize: true }
+	}
+}
+
+impl ObservationConfig {
+	// Number of values in an observation, the input size of networks
+	pub fn observation_size(&self) -> usize {
+		3 + 2 * usize::from(self.ball_velocity) + usize::from(self.paddle_velocity) + usize::from(self.opponent)
+	}
+
+	pub fn observe(&self, view: &PaddleView, arena: &Arena) -> Vec<f32> {
+		let (position_scale, ball_scale, paddle_scale) = match self.normalize {
+			true  => (view.turn(arena.size / 2.0).abs(), BALL_STARTING_SPEED * 2.0, PLAYER_MAX_SPEED),
+			false => (Vec2::ONE, 1.0, 1.0),
+		};
+
+		let mut observation = Vec::with_capacity(self.observation_size());
+		observation.extend((view.ball_position / position_scale).to_array());
+		if self.ball_velocity {
+			observation.extend((view.ball_velocity / ball_scale).to_array());
+		}
+		observation.push(view.paddle_position / position_scale.y);
+		if self.paddle_velocity {
+			observation.push(view.paddle_velocity / paddle_scale);
+		}
+		if self.opponent {
+			observation.push(view.opponent_position / position_scale.y);
+		}
+		observation
+	}
+}
+
+impl PaddleView {
+	// Ball in world space, paddles along the axis of their sides
+	pub fn new(side: Side, ball_position: Vec2, ball_velocity: Vec2, paddle: (f32, f32), opponent_position: f32) -> Self {
+		let mut view = Self { side: Some(side), ..default() };
+		view.ball_position = view.turn(ball_position);
+		view.ball_velocity = view.turn(ball_velocity);
+		view.paddle_position = paddle.0;
+		view.paddle_velocity = paddle.1;
+		view.opponent_position = opponent_position;
+		view
+	}
+
+	pub fn from_simulation(simulation: &Simulation, index: usize) -> Self {
+		Self::new(
+			SIDES[index],
+			simulation.ball_position,
+			simulation.ball_velocity,
+			(simulation.paddle_positions[index], simulation.paddle_velocities[index]),
+			simulation.paddle_positions[1 - index],
+		)
+	}
+
+	// World vector to the view, x away from the wall and y along the paddle axis
+	fn turn(&self, vector: Vec2) -> Vec2 {
+		match self.side {
+			Some(side) => Vec2::new(-vector.dot(side.normal()), vector.dot(side.axis())),
+			None       => vector,
+		}
+	}
+}
+
+impl Default for RewardConfig {
+	fn default() -> Self {
+		Self { hit: 0.1, score: 1.0, concede: -1.0 }
+	}
+}
+
+impl Default for EnvConfig {
+	fn default() -> Self {
+		Self {
+			observation: ObservationConfig::default(),
+			reward: RewardConfig::default(),
+			max_ticks: 60 * 60 * 5,
+			skip_delays: true,
+			arena: Arena::default(),
+			opponent: HeadlessAi::new(OPPONENT_REACTION_DELAY, OPPONENT_AIM_ERROR, true),
+		}
+	}
+}
+
+impl Env {
+	pub fn new(config: EnvConfig) -> Self {
+		Self { opponent: config.opponent, config, simulation: Simulation::default(), rng: StdRng::seed_from_u64(0) }
+	}
+
+	pub fn config(&self) -> &EnvConfig {
+		&self.config
+	}
+
+	pub fn simulation(&self) -> &Simulation {
+		&self.simulation
+	}
+
+	pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+		self.rng = StdRng::seed_from_u64(seed);
+		self.simulation = Simulation::default();
+		self.opponent = self.config.opponent;
+		self.prepare_serve();
+		self.observation()
+	}
+
+	pub fn step(&mut self, action: Action) -> StepResult {
+		let mut inputs = [PaddleInput::default(); 2];
+		inputs[AGENT] = action.input();
+		inputs[OPPONENT] = self.opponent.input(&self.simulation, OPPONENT, &self.config.arena, &mut self.rng);
+
+		let events = self.simulation.step(inputs, &self.config.arena);
+		let reward = &self.config.reward;
+		let mut total = 0.0;
+		if events.hit == Some(AGENT) { total += reward.hit }
+		if events.goal == Some(SIDES[OPPONENT]) { total += reward.score }
+		if events.goal == Some(SIDES[AGENT]) { total += reward.concede }
+
+		self.prepare_serve();
+		StepResult {
+			observation: self.observation(),
+			reward: total,
+			done: self.simulation.phase == Phase::GameOver || self.simulation.tick >= self.config.max_ticks,
+		}
+	}
+
+	pub fn observation(&self) -> Vec<f32> {
+		self.config.observation.observe(&PaddleView::from_simulation(&self.simulation, AGENT), &self.config.arena)
+	}
+
+	fn prepare_serve(&mut self) {
+		self.simulation.prepare_serve(&mut self.rng, self.config.skip_delays);
+	}
+}
+
+impl BatchEnv {
+	pub fn new(config: EnvConfig, count: usize) -> Self {
+		Self {
+			envs: (0..count).map(|_| Env::new(config.clone())).collect(),
+			episodes: vec![0; count],
+			seeds: vec![0; count],
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.envs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.envs.is_empty()
+	}
+
+	// Environment i starts from seed + i
+	pub fn reset(&mut self, seed: u64) -> Vec<Vec<f32>> {
+		self.episodes.fill(0);
+		self.envs.iter_mut()
+			.zip(&mut self.seeds)
+			.enumerate()
+			.map(|(index, (env, env_seed))| {
+				*env_seed = seed.wrapping_add(index as u64);
+				env.reset(*env_seed)
+			})
+			.collect()
+	}
+
+	// Results of finished episodes carry the first observation of the next one
+	pub fn step(&mut self, actions: &[Action]) -> Vec<StepResult> {
+		self.envs.iter_mut()
+			.zip(actions)
+			.zip(self.episodes.iter_mut().zip(&self.seeds))
+			.map(|((env, action), (episode, seed))| {
+				let mut result = env.step(*action);
+				if result.done {
+					*episode += 1;
+					result.observation = env.reset(seed.wrapping_add(*episode << 32));
+				}
+				result
+			})
+			.collect()
+	}
+}
+
+// pyo3 expands fallible methods into conversions clippy considers useless
+#[cfg(feature = "python")]
+#[allow(clippy::useless_conversion)]
+mod python {
+	use pyo3::prelude::*;
+	use pyo3::exceptions::PyValueError;
+
+	use super::{Action, BatchEnv, EnvConfig, ObservationConfig, RewardConfig};
+
+	// Observations, rewards and done flags of every environment
+	type BatchResult = (Vec<Vec<f32>>, Vec<f32>, Vec<bool>);
+
+	fn action(index: usize) -> PyResult<Action> {
+		Action::from_index(index).ok_or_else(|| PyValueError::new_err("action is 0 (stay), 1 (up) or 2 (down)"))
+	}
+
+	// Flat copy of EnvConfig with settable attributes
+	#[pyclass(name = "Config")]
+	#[derive(Clone)]
+	struct PyConfig {
+		#[pyo3(get, set)] ball_velocity: bool,
+		#[pyo3(get, set)] paddle_velocity: bool,
+		#[pyo3(get, set)] opponent: bool,
+		#[pyo3(get, set)] normalize: bool,
+		#[pyo3(get, set)] hit: f32,
+		#[pyo3(get, set)] score: f32,
+		#[pyo3(get, set)] concede: f32,
+		#[pyo3(get, set)] max_ticks: u32,
+		#[pyo3(get, set)] skip_delays: bool,
+	}
+
+	impl From<&PyConfig> for EnvConfig {
+		fn from(config: &PyConfig) -> Self {
+			EnvConfig {
+				observation: ObservationConfig {
+					ball_velocity: config.ball_velocity,
+					paddle_velocity: config.paddle_velocity,
+					opponent: config.opponent,
+					normalize: config.normalize,
+				},
+				reward: RewardConfig { hit: config.hit, score: config.score, concede: config.concede },
+				max_ticks: config.max_ticks,
+				skip_delays: config.skip_delays,
+				..EnvConfig::default()
+			}
+		}
+	}
+
+	#[pymethods]
+	impl PyConfig {
+		#[new]
+		fn new() -> Self {
+			let config = EnvConfig::default();
+			Self {
+				ball_velocity: config.observation.ball_velocity,
+				paddle_velocity: config.observation.paddle_velocity,
+				opponent: config.observation.opponent,
+				normalize: config.observation.normalize,
+				hit: config.reward.hit,
+				score: config.reward.score,
+				concede: config.reward.concede,
+				max_ticks: config.max_ticks,
+				skip_delays: config.skip_delays,
+			}
+		}
+	}
+
+	#[pyclass(name = "Env")]
+	struct PyEnv(super::Env);
+
+	#[pymethods]
+	impl PyEnv {
+		#[new]
+		#[pyo3(signature = (config=None))]
+		fn new(config: Option<PyConfig>) -> Self {
+			Self(super::Env::new(config.map_or_else(EnvConfig::default, |config| EnvConfig::from(&config))))
+		}
+
+		#[pyo3(signature = (seed=0))]
+		fn reset(&mut self, seed: u64) -> Vec<f32> {
+			self.0.reset(seed)
+		}
+
+		fn step(&mut self, action_index: usize) -> PyResult<(Vec<f32>, f32, bool)> {
+			let result = self.0.step(action(action_index)?);
+			Ok((result.observation, result.reward, result.done))
+		}
+	}
+
+	#[pyclass(name = "BatchEnv")]
+	struct PyBatchEnv(BatchEnv);
+
+	#[pymethods]
+	impl PyBatchEnv {
+		#[new]
+		#[pyo3(signature = (count, config=None))]
+		fn new(count: usize, config: Option<PyConfig>) -> Self {
+			Self(BatchEnv::new(config.map_or_else(EnvConfig::default, |config| EnvConfig::from(&config)), count))
+		}
+
+		#[pyo3(signature = (seed=0))]
+		fn reset(&mut self, seed: u64) -> Vec<Vec<f32>> {
+			self.0.reset(seed)
+		}
+
+		fn step(&mut self, action_indexes: Vec<usize>) -> PyResult<BatchResult> {
+			if action_indexes.len() != self.0.len() {
+				return Err(PyValueError::new_err("one action per environment"));
+			}
+			let actions = action_indexes.into_iter().map(action).collect::<PyResult<Vec<_>>>()?;
+			let results = self.0.step(&actions);
+			Ok((
+				results.iter().map(|result| result.observation.clone()).collect(),
+				results.iter().map(|result| result.reward).collect(),
+				results.iter().map(|result| result.done).collect(),
+			))
+		}
+	}
+
+	#[pymodule]
+	fn pong(module: &Bound<'_, PyModule>) -> PyResult<()> {
+		module.add_class::<PyConfig>()?;
+		module.add_class::<PyEnv>()?;
+		module.add_class::<PyBatchEnv>()?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Observations, rewards and done flags of an episode with a fixed pattern of actions
+	fn play(seed: u64, ticks: usize) -> Vec<(Vec<f32>, f32, bool)> {
+		let mut env = Env::new(EnvConfig::default());
+		let first = env.reset(seed);
+		std::iter::once((first, 0.0, false))
+			.chain((0..ticks).map(|tick| {
+				let result = env.step(Action::ALL[tick / 7 % 3]);
+				(result.observation, result.reward, result.done)
+			}))
+			.collect()
+	}
+
+	#[test]
+	fn seeded_episodes_repeat() {
+		assert_eq!(play(5, 900), play(5, 900));
+		assert_ne!(play(5, 900), play(6, 900));
+	}
+
+	#[test]
+	fn observations_match_their_size() {
+		let env = Env::new(EnvConfig::default());
+		assert_eq!(env.observation().len(), env.config().observation.observation_size());
+
+		let observation = ObservationConfig { ball_velocity: false, paddle_velocity: false, opponent: false, normalize: false };
+		let env = Env::new(EnvConfig { observation, ..default() });
+		assert_eq!(env.observation().len(), observation.observation_size());
+	}
+
+	#[test]
+	fn conceded_goals_are_punished_until_the_match_ends() {
+		let reward = RewardConfig { hit: 0.0, score: 10.0, concede: -1.0 };
+		let mut env = Env::new(EnvConfig { reward, ..default() });
+		env.reset(1);
+
+		let (mut scored, mut conceded) = (0, 0);
+		loop {
+			let result = env.step(Action::Stay);
+			match result.reward {
+				10.0 => scored += 1,
+				-1.0 => conceded += 1,
+				_    => assert_eq!(result.reward, 0.0),
+			}
+			if result.done { break }
+		}
+
+		let simulation = env.simulation();
+		assert!(conceded > 0);
+		assert_eq!(simulation.conceded[AGENT], conceded);
+		assert_eq!(simulation.conceded[OPPONENT], scored);
+		assert!(simulation.phase == Phase::GameOver || simulation.tick >= env.config().max_ticks);
+	}
+
+	#[test]
+	fn episodes_are_cut_after_max_ticks() {
+		let mut env = Env::new(EnvConfig { max_ticks: 10, ..default() });
+		env.reset(0);
+		let done: Vec<bool> = (0..10).map(|_| env.step(Action::Stay).done).collect();
+		assert_eq!(done, [false, false, false, false, false, false, false, false, false, true]);
+	}
+}
@@ -0,0 +1,374 @@
+// Paddles steered by the program: the built-in AI, external bots and learned networks.
+// The adaptive AI changes its skill between points to win about as often as asked.
+
+// import std
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::core::FrameCount;
+
+// import custom
+use rand::Rng;
+
+// import crate
+use crate::game::{init_shared_resources, Ball, Collider, Intent, MaxSpeed, NetworkView, Paddle, Scoreboard, Settings, Velocity};
+use crate::game::input::{InputSet, Player};
+use crate::game::state::GameplayState;
+use crate::{ai_target, Arena, PaddleMotion, Side, PADDLE_SIZE};
+use crate::bot::{BallState, BotLink, BotState, PaddleState};
+use crate::env::{ObservationConfig, PaddleView};
+use crate::neural::NeuralNetwork;
+use crate::simulation::PaddleInput;
+
+// Intents of the paddles the program steers, before physics moves them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub struct AiSet;
+
+pub const AI_STARTING_MAX_SPEED: f32 = 500.0;
+
+// Adaptive AI: parameters at the lowest and the highest level
+const ADAPTIVE_REACTION_DELAY: [f32; 2] = [0.3, 0.0]; // seconds between looks at the ball
+const ADAPTIVE_MAX_SPEED: [f32; 2]      = [250.0, 900.0];
+const ADAPTIVE_AIM_ERROR: [f32; 2]      = [PADDLE_SIZE.y * 0.6, 0.0];
+const ADAPTIVE_STARTING_LEVEL: f32      = 0.5;
+const ADAPTIVE_RATE: f32                = 0.25; // level change per point at the largest miss of the target
+const ADAPTIVE_WINDOW: usize            = 10;   // recent points weighed
+
+// Components
+#[derive(Component)] pub struct Ai;
+#[derive(Component)] pub struct Offensive; // Ai aiming returns away from the opponent
+#[derive(Component, Default)] pub struct AiPlan {
+	pub reaction: Timer,
+	pub target: Option<f32>, // position along the side axis the paddle heads for
+	pub aim_error: f32,
+	pub was_approaching: bool,
+}
+#[derive(Component, Deref)] pub struct NeuralAi(pub NeuralNetwork);
+
+// Resources
+#[derive(Resource, Debug, Clone, Copy)] pub struct AiSkill {
+	pub reaction_delay: f32, // seconds
+	pub max_speed: f32,
+	pub aim_error: f32, // largest offset of the aim from the ball
+}
+#[derive(Resource)] pub struct AdaptiveAi {
+	pub target: f32, // win probability of the AI per point
+	pub level: f32,  // 0 is the weakest, 1 the strongest
+	pub recent: VecDeque<bool>, // points won by the AI, newest last
+	pub won: u32,
+	pub lost: u32,
+	conceded: [u32; 4], // scoreboard at the last point
+}
+
+pub struct AiPlugin;
+impl Plugin for AiPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Resources
+		let settings = app.world.resource::<Settings>().clone();
+		if let Some(target) = settings.adaptive_ai {
+			app.insert_resource(AdaptiveAi::new(target));
+		}
+		app.insert_resource(AiSkill::new(&settings));
+
+		// Transitions
+		app.add_systems(OnExit(GameplayState::Active), adapt_ai.run_if(resource_exists::<AdaptiveAi>));
+
+		// Systems: update, the networked game is steered by the peers
+		app.configure_sets(Update, AiSet
+				.after(InputSet)
+				.run_if(not(in_state(GameplayState::Startup)))
+				.run_if(not(resource_exists::<NetworkView>)))
+			.add_systems(Update,
+				(
+				ai_control,
+				bot_control,
+				neural_control,
+				)
+				.in_set(AiSet)
+				);
+	}
+}
+
+impl AiSkill {
+	pub fn new(settings: &Settings) -> Self {
+		match settings.adaptive_ai {
+			Some(_) => Self::at_level(ADAPTIVE_STARTING_LEVEL),
+			None    => Self { reaction_delay: 0.0, max_speed: AI_STARTING_MAX_SPEED, aim_error: 0.0 },
+		}
+	}
+
+	pub fn at_level(level: f32) -> Self {
+		let lerp = |range: [f32; 2]| range[0] + (range[1] - range[0]) * level;
+		Self {
+			reaction_delay: lerp(ADAPTIVE_REACTION_DELAY),
+			max_speed: lerp(ADAPTIVE_MAX_SPEED),
+			aim_error: lerp(ADAPTIVE_AIM_ERROR),
+		}
+	}
+}
+
+impl AiPlan {
+	// Position along the side axis the paddle heads for, looking at the ball through look once per reaction delay
+	fn update(&mut self, skill: &AiSkill, delta: Duration, is_approaching: bool, look: impl FnOnce() -> f32) -> f32 {
+		// a new aim error every time a ball heads for the paddle
+		if is_approaching && !self.was_approaching {
+			self.aim_error = rand::thread_rng().gen_range(-1.0..=1.0) * skill.aim_error;
+		}
+		self.was_approaching = is_approaching;
+
+		// the ball is looked at once per reaction delay, the paddle heads for where it was seen
+		self.reaction.tick(delta);
+		if self.reaction.finished() || self.target.is_none() {
+			self.reaction = Timer::from_seconds(skill.reaction_delay, TimerMode::Once);
+			self.target = Some(look() + self.aim_error);
+		}
+		self.target.unwrap_or_default()
+	}
+}
+
+impl AdaptiveAi {
+	pub fn new(target: f32) -> Self {
+		Self { target, level: ADAPTIVE_STARTING_LEVEL, recent: VecDeque::new(), won: 0, lost: 0, conceded: [0; 4] }
+	}
+
+	pub fn recent_win_rate(&self) -> f32 {
+		self.recent.iter().filter(|is_won| **is_won).count() as f32 / self.recent.len().max(1) as f32
+	}
+}
+
+fn ai_control(
+	mut paddle_query: Query<(Option<&mut AiPlan>, &Transform, &Collider, &Velocity, &mut Intent, &PaddleMotion, &MaxSpeed, &Side, Has<Offensive>), (With<Paddle>, Without<BotLink>)>,
+	ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+	skill: Res<AiSkill>,
+	arena: Res<Arena>,
+	time: Res<Time>,
+) {
+	let paddles: Vec<(Side, f32)> = paddle_query.iter()
+		.map(|(_, transform, .., side, _)| (*side, transform.translation.xy().dot(side.axis())))
+		.collect();
+
+	for (plan, transform, collider, velocity, mut intent, motion, max_speed, side, is_offensive) in &mut paddle_query {
+		let Some(mut plan) = plan else { continue };
+		let paddle = transform.translation.xy();
+		let balls = ball_query.iter().map(|(ball_transform, ball_velocity)| (ball_transform.translation.xy(), ball_velocity.0));
+		let Some(ball) = most_threatening_ball(*side, paddle, balls) else { continue };
+
+		let target = plan.update(&skill, time.delta(), ball.1.dot(side.normal()) > 0.0, || {
+			// offensive AI plays the ball off the paddle towards the far end from the opponent once it can make it
+			let opponent = paddles.iter()
+				.find(|(other, _)| other.normal() == -side.normal())
+				.map_or(0.0, |(_, position)| *position);
+			// tuning and power-ups change the size of the paddle
+			let paddle_size = collider.0 * transform.scale.xy();
+			ai_target(*side, paddle.dot(side.axis()), paddle_size, max_speed.0, ball, is_offensive.then_some(opponent), &arena)
+		});
+
+		// the paddle speeds up and brakes like any other to get there
+		intent.0 = motion.intent_towards(paddle.dot(side.axis()), velocity.dot(side.axis()), target);
+	}
+}
+
+// External programs steer like players, the plan of the built-in AI takes over when they don't answer in time
+fn bot_control(
+	mut paddle_query: Query<(Option<(&mut BotLink, &mut AiPlan)>, &Transform, &Collider, &Velocity, &mut Intent, &PaddleMotion, &MaxSpeed, &Side), With<Paddle>>,
+	ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+	scoreboard: Res<Scoreboard>,
+	skill: Res<AiSkill>,
+	arena: Res<Arena>,
+	state: Res<State<GameplayState>>,
+	frame: Res<FrameCount>,
+	time: Res<Time>,
+) {
+	if time.delta_seconds() == 0.0 { return }
+
+	let balls: Vec<BallState> = ball_query.iter()
+		.map(|(transform, velocity)| BallState {
+			position: transform.translation.xy().to_array(),
+			velocity: velocity.to_array(),
+		})
+		.collect();
+	let paddles: Vec<PaddleState> = paddle_query.iter()
+		.map(|(_, transform, _, velocity, .., side)| PaddleState {
+			side: side.name(),
+			position: transform.translation.xy().to_array(),
+			velocity: velocity.to_array(),
+			conceded: scoreboard.conceded[*side as usize],
+		})
+		.collect();
+
+	for (bot, transform, collider, velocity, mut intent, motion, max_speed, side) in &mut paddle_query {
+		let Some((mut link, mut plan)) = bot else { continue };
+		let bot_state = BotState {
+			tick: u64::from(frame.0),
+			state: format!("{:?}", state.get()),
+			side: side.name(),
+			balls: balls.clone(),
+			paddles: paddles.clone(),
+		};
+
+		// answers arrive in later frames, the game never waits for them
+		let answer = link.poll();
+		link.send(&bot_state);
+
+		// the plan follows the ball even while the bot answers, to be ready when it doesn't
+		let paddle = transform.translation.xy();
+		let threat = most_threatening_ball(*side, paddle, balls.iter().map(|ball| (Vec2::from_array(ball.position), Vec2::from_array(ball.velocity))));
+		let target = threat.map(|ball| plan.update(&skill, time.delta(), ball.1.dot(side.normal()) > 0.0, || {
+			let paddle_size = collider.0 * transform.scale.xy();
+			ai_target(*side, paddle.dot(side.axis()), paddle_size, max_speed.0, ball, None, &arena)
+		}));
+
+		match (answer, target) {
+			(Some(direction), _) => intent.0 = direction,
+			(None, Some(target)) => intent.0 = motion.intent_towards(paddle.dot(side.axis()), velocity.dot(side.axis()), target),
+			(None, None) => {}
+		}
+	}
+}
+
+// Learned AIs see the game like in training and steer like players
+fn neural_control(
+	mut paddle_query: Query<(Option<&NeuralAi>, &Transform, &Velocity, &mut Intent, &Side), With<Paddle>>,
+	ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+	arena: Res<Arena>,
+	time: Res<Time>,
+) {
+	if time.delta_seconds() == 0.0 { return }
+
+	let paddles: Vec<(Side, f32)> = paddle_query.iter()
+		.map(|(_, transform, .., side)| (*side, transform.translation.xy().dot(side.axis())))
+		.collect();
+
+	for (network, transform, velocity, mut intent, side) in &mut paddle_query {
+		let Some(network) = network else { continue };
+		let balls = ball_query.iter().map(|(ball_transform, ball_velocity)| (ball_transform.translation.xy(), ball_velocity.0));
+		let Some((ball_position, ball_velocity)) = most_threatening_ball(*side, transform.translation.xy(), balls) else { continue };
+		let opponent = paddles.iter()
+			.find(|(other, _)| other.normal() == -side.normal())
+			.map_or(0.0, |(_, position)| *position);
+
+		let view = PaddleView::new(
+			*side,
+			ball_position,
+			ball_velocity,
+			(transform.translation.xy().dot(side.axis()), velocity.dot(side.axis())),
+			opponent,
+		);
+		// Buttons like in training
+		intent.0 = PaddleInput::from_direction(network.direction(&ObservationConfig::default().observe(&view, &arena))).direction();
+	}
+}
+
+// Between points the AI gets stronger when it loses more often than the target and weaker otherwise
+fn adapt_ai(
+	mut adaptive: ResMut<AdaptiveAi>,
+	mut skill: ResMut<AiSkill>,
+	scoreboard: Res<Scoreboard>,
+	mut paddle_query: Query<(&mut MaxSpeed, &Side, Has<Ai>, Has<Player>), With<Paddle>>,
+) {
+	let conceded = adaptive.conceded;
+	adaptive.conceded = scoreboard.conceded;
+
+	// points between the AI and human players only
+	let mut outcomes = Vec::new();
+	for (_, side, is_ai, is_player) in &paddle_query {
+		let goals = scoreboard.conceded[*side as usize].saturating_sub(conceded[*side as usize]);
+		if is_ai     { outcomes.extend((0..goals).map(|_| false)) }
+		if is_player { outcomes.extend((0..goals).map(|_| true)) }
+	}
+	if outcomes.is_empty() { return }
+
+	for is_won in outcomes {
+		if is_won { adaptive.won += 1 } else { adaptive.lost += 1 }
+		adaptive.recent.push_back(is_won);
+		if adaptive.recent.len() > ADAPTIVE_WINDOW { adaptive.recent.pop_front(); }
+	}
+	let miss = adaptive.target - adaptive.recent_win_rate();
+	adaptive.level = (adaptive.level + ADAPTIVE_RATE * miss).clamp(0.0, 1.0);
+
+	// max speed scales so power-up factors survive
+	let previous = *skill;
+	*skill = AiSkill::at_level(adaptive.level);
+	for (mut max_speed, _, is_ai, _) in &mut paddle_query {
+		if is_ai { max_speed.0 *= skill.max_speed / previous.max_speed }
+	}
+
+	info!(
+		"AI: level {:.2}, won {} of the last {} points, reaction {:.2}s, max speed {:.0}, aim error {:.0}",
+		adaptive.level, adaptive.recent.iter().filter(|is_won| **is_won).count(), adaptive.recent.len(),
+		skill.reaction_delay, skill.max_speed, skill.aim_error,
+	);
+}
+
+// Ball that reaches the wall of the side first, or the closest one if none is approaching
+pub fn most_threatening_ball(
+	side: Side,
+	paddle: Vec2,
+	balls: impl Iterator<Item = (Vec2, Vec2)>,
+) -> Option<(Vec2, Vec2)> {
+	balls
+		.map(|(position, velocity)| {
+			let distance = (paddle - position).dot(side.normal()).max(0.0);
+			let speed = velocity.dot(side.normal());
+			let time_to_reach = if speed > 0.0 { distance / speed } else { f32::INFINITY };
+			(position, velocity, time_to_reach, position.distance(paddle))
+		})
+		.min_by(|a, b| a.2.total_cmp(&b.2).then(a.3.total_cmp(&b.3)))
+		.map(|(position, velocity, ..)| (position, velocity))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::TcpListener;
+	use crate::game::tests::{start_match, test_app, FRAME};
+	use crate::game::Tuning;
+
+	#[test]
+	fn ai_paddle_obeys_the_paddle_motion() {
+		let mut app = test_app();
+		start_match(&mut app);
+
+		let tuning = Tuning::default();
+		let max_delta = tuning.player_acceleration.max(tuning.player_deceleration).max(tuning.paddle_friction) * FRAME.as_secs_f32();
+		let mut query = app.world.query_filtered::<(&Velocity, &MaxSpeed), (With<Ai>, With<Paddle>)>();
+		let mut previous = query.single(&app.world).0.0;
+		for _ in 0..120 {
+			app.update();
+			let (velocity, max_speed) = query.single(&app.world);
+			// only the end of the arena stops a paddle at once
+			let is_stopped = velocity.0 == Vec2::ZERO;
+			assert!(is_stopped || (velocity.0 - previous).length() <= max_delta + 0.01, "AI paddle jumped from {previous} to {}", velocity.0);
+			assert!(velocity.length() <= max_speed.0 + 0.01);
+			previous = velocity.0;
+		}
+	}
+
+	#[test]
+	fn late_bots_are_steered_by_the_plan_of_the_ai() {
+		let mut app = test_app();
+		start_match(&mut app);
+		app.insert_resource(AiSkill { reaction_delay: 0.5, max_speed: AI_STARTING_MAX_SPEED, aim_error: 0.0 });
+
+		// a bot that never answers takes over the AI paddle
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let link = BotLink::open(&format!("tcp:{}", listener.local_addr().unwrap()), Duration::ZERO).unwrap();
+		let paddle = app.world.query_filtered::<Entity, With<Ai>>().single(&app.world);
+		app.world.entity_mut(paddle).remove::<Ai>().insert((link, AiPlan::default()));
+
+		// the ball is looked at once per reaction delay while it moves on
+		let plan = |app: &App| app.world.get::<AiPlan>(paddle).unwrap().target;
+		app.update();
+		let seen = plan(&app);
+		assert!(seen.is_some());
+		for _ in 0..10 {
+			app.update();
+			assert_eq!(plan(&app), seen);
+		}
+		for _ in 0..30 { app.update() }
+		assert_ne!(plan(&app), seen);
+	}
+}
@@ -0,0 +1,401 @@
+// Arena artwork, custom arenas with obstacles and goal mouths, and the tuning of local matches.
+// Both files live in assets and are hot reloaded with the hot_reload feature.
+
+// import std
+use std::f32::consts::TAU;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::BoxedFuture;
+
+// import custom
+use bevy_vello::prelude::*;
+use serde::Deserialize;
+
+// import crate
+use crate::game::{init_shared_resources, Collider, Eliminated, MaxSpeed, Paddle, Settings, Tuning, ZLAYER};
+use crate::game::ai::Ai;
+use crate::{Arena, GameMode, PaddleMotion, Side};
+
+pub const TUNING_PATH: &str = "tuning.ron"; // in assets, hot reloaded with the hot_reload feature
+
+const OBSTACLE_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+const WALL_COLOR: Color     = Color::rgb(0.25, 0.25, 0.25);
+
+// Components
+#[derive(Component)] pub struct ArenaArtwork;
+#[derive(Component)] pub struct Obstacle { position: Vec2, motion: Option<Motion> }
+#[derive(Component)] pub struct GoalPost(Side);
+
+// Resources
+#[derive(Resource, Deref, DerefMut)] pub struct ArenaHandle(pub Handle<ArenaDefinition>);
+#[derive(Resource, Deref, DerefMut)] pub struct TuningHandle(pub Handle<Tuning>);
+
+// Assets
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)] pub struct ArenaDefinition {
+	size: (f32, f32),
+	wall_thickness: f32,
+	artwork: String,
+	#[serde(default)]
+	goal_size: Option<f32>,
+	#[serde(default)]
+	obstacles: Vec<ObstacleDefinition>,
+}
+#[derive(Deserialize, Debug, Clone)] struct ObstacleDefinition {
+	position: (f32, f32),
+	size: (f32, f32),
+	#[serde(default)]
+	motion: Option<Motion>,
+}
+// Obstacle swings around its position by `offset` once per `period` seconds
+#[derive(Deserialize, Debug, Clone, Copy)] struct Motion {
+	offset: (f32, f32),
+	period: f32,
+}
+
+// Loaders
+struct ArenaLoader;
+impl AssetLoader for ArenaLoader {
+	type Asset = ArenaDefinition;
+	type Settings = ();
+	type Error = Box<dyn std::error::Error + Send + Sync>;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a (),
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+			Ok(ron::de::from_bytes(&bytes)?)
+		})
+	}
+
+	fn extensions(&self) -> &[&str] {
+		&["arena.ron"]
+	}
+}
+
+struct TuningLoader;
+impl AssetLoader for TuningLoader {
+	type Asset = Tuning;
+	type Settings = ();
+	type Error = Box<dyn std::error::Error + Send + Sync>;
+
+	fn load<'a>(
+		&'a self,
+		reader: &'a mut Reader,
+		_settings: &'a (),
+		_load_context: &'a mut LoadContext,
+	) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+		Box::pin(async move {
+			let mut bytes = Vec::new();
+			reader.read_to_end(&mut bytes).await?;
+
+			// a broken file keeps the previous values
+			let tuning: Tuning = ron::de::from_bytes(&bytes)?;
+			tuning.validate()?;
+			Ok(tuning)
+		})
+	}
+
+	// the full extension of tuning.ron is only "ron", arenas keep theirs with the longer "arena.ron"
+	fn extensions(&self) -> &[&str] {
+		&["ron"]
+	}
+}
+
+pub struct ArenaPlugin;
+impl Plugin for ArenaPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Assets
+		app.init_asset::<ArenaDefinition>()
+			.register_asset_loader(ArenaLoader)
+			.init_asset::<Tuning>()
+			.register_asset_loader(TuningLoader);
+
+		// Systems: startup
+		app.add_systems(Startup, arena_setup);
+
+		// Systems: update
+		app.add_systems(Update, (
+			apply_tuning,
+			apply_arena,
+			move_obstacles,
+			update_goal_posts.run_if(resource_changed::<Arena>),
+			show_goal_posts,
+			).chain());
+	}
+}
+
+fn arena_setup(
+	mut commands: Commands,
+	asset_server: Res<AssetServer>,
+	settings: Res<Settings>,
+) {
+	// Frame
+	let frame = match settings.mode {
+		GameMode::Versus      => "embedded://textures/frame.svg",
+		GameMode::FourPlayers => "embedded://textures/frame_four_players.svg",
+	};
+	commands.spawn((
+		ArenaArtwork,
+		VelloAssetBundle {
+			vector: asset_server.load(frame),
+			debug_visualizations: DebugVisualizations::Hidden,
+			transform: Transform::from_xyz(0.0, 0.0, ZLAYER::FRAME).with_scale(Vec3::splat(1.0)),
+			..default()
+		},
+	));
+
+	// Custom arena replaces the frame once loaded
+	if let Some(path) = &settings.arena {
+		commands.insert_resource(ArenaHandle(asset_server.load(path.clone())));
+	}
+
+	// Tuning replaces the built-in values once loaded, networked matches simulate with the built-in ones
+	if !settings.is_networked() {
+		commands.insert_resource(TuningHandle(asset_server.load(TUNING_PATH)));
+	}
+}
+
+fn apply_arena(
+	mut commands: Commands,
+	mut asset_events: EventReader<AssetEvent<ArenaDefinition>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	mut arena: ResMut<Arena>,
+	definitions: Res<Assets<ArenaDefinition>>,
+	asset_server: Res<AssetServer>,
+	handle: Option<Res<ArenaHandle>>,
+	settings: Res<Settings>,
+	tuning: Res<Tuning>,
+	mut artwork_query: Query<&mut Handle<VelloAsset>, With<ArenaArtwork>>,
+	mut projection_query: Query<&mut OrthographicProjection>,
+	mut paddle_query: Query<(&mut Transform, &Side), With<Paddle>>,
+	obstacle_query: Query<Entity, With<Obstacle>>,
+) {
+	let Some(handle) = handle else { return };
+
+	// Initial load and every hot reload of the file
+	let is_changed = asset_events.read().any(|event| event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0));
+	if !is_changed { return }
+	let Some(definition) = definitions.get(&handle.0) else { return };
+
+	*arena = Arena {
+		size: Vec2::new(definition.size.0, definition.size.1),
+		wall_thickness: definition.wall_thickness,
+		goal_size: definition.goal_size.or(settings.goal_size),
+	};
+	info!("ARENA: {:?}", *arena);
+
+	for mut artwork in &mut artwork_query {
+		*artwork = asset_server.load(definition.artwork.clone());
+	}
+	for mut projection in &mut projection_query {
+		projection.scaling_mode = arena.projection();
+	}
+	for (mut transform, side) in &mut paddle_query {
+		let bound = settings.mode.sized_paddle_bound(*side, &arena, tuning.paddle_size(Side::Left));
+		let along_axis = transform.translation.xy().dot(side.axis()).clamp(-bound, bound);
+		let position = side.paddle_position(&arena) + side.axis() * along_axis;
+		transform.translation = position.extend(transform.translation.z);
+	}
+
+	// Obstacles
+	for obstacle in &obstacle_query {
+		commands.entity(obstacle).despawn();
+	}
+	let material = materials.add(OBSTACLE_COLOR);
+	for obstacle in &definition.obstacles {
+		let position = Vec2::new(obstacle.position.0, obstacle.position.1);
+		let size = Vec2::new(obstacle.size.0, obstacle.size.1);
+		commands.spawn((
+			Obstacle { position, motion: obstacle.motion },
+			Collider(size),
+			MaterialMesh2dBundle {
+				mesh: Mesh2dHandle(meshes.add(Rectangle::from_size(size))),
+				material: material.clone(),
+				transform: Transform::from_translation(position.extend(ZLAYER::MAIN)),
+				..default()
+			},
+		));
+	}
+}
+
+fn apply_tuning(
+	mut asset_events: EventReader<AssetEvent<Tuning>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut tuning: ResMut<Tuning>,
+	tunings: Res<Assets<Tuning>>,
+	handle: Option<Res<TuningHandle>>,
+	mut paddle_query: Query<(&mut Collider, &mut Mesh2dHandle, &mut MaxSpeed, &mut PaddleMotion, &Side, Has<Ai>), With<Paddle>>,
+	mut bloom_query: Query<&mut BloomSettings>,
+) {
+	let Some(handle) = handle else { return };
+
+	// Initial load and every hot reload of the file
+	let is_changed = asset_events.read().any(|event| event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0));
+	if !is_changed { return }
+	let Some(loaded) = tunings.get(&handle.0) else { return };
+
+	let speed_ratio = loaded.player_max_speed / tuning.player_max_speed;
+	*tuning = loaded.clone();
+	info!("TUNING: {:?}", *tuning);
+
+	// Balls pick up the new speeds at the next set, paddles at once
+	for (mut collider, mut mesh, mut max_speed, mut motion, side, is_ai) in &mut paddle_query {
+		collider.0 = tuning.paddle_size(*side);
+		*motion = tuning.paddle_motion();
+		*mesh = Mesh2dHandle(meshes.add(Rectangle::from_size(collider.0)));
+		if !is_ai { max_speed.0 *= speed_ratio }
+	}
+	for mut bloom in &mut bloom_query {
+		*bloom = tuning.bloom.settings();
+	}
+}
+
+fn move_obstacles(
+	mut query: Query<(&mut Transform, &Obstacle)>,
+	time: Res<Time>,
+) {
+	for (mut transform, obstacle) in &mut query {
+		let Some(motion) = obstacle.motion else { continue };
+		if motion.period <= 0.0 { continue }
+
+		let phase = (time.elapsed_seconds() * TAU / motion.period).sin();
+		let position = obstacle.position + Vec2::new(motion.offset.0, motion.offset.1) * phase;
+		transform.translation = position.extend(transform.translation.z);
+	}
+}
+
+// Solid parts of the walls beside goal mouths
+fn update_goal_posts(
+	mut commands: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	arena: Res<Arena>,
+	settings: Res<Settings>,
+	post_query: Query<Entity, With<GoalPost>>,
+) {
+	for post in &post_query {
+		commands.entity(post).despawn();
+	}
+
+	let Some(goal_size) = arena.goal_size else { return };
+	let material = materials.add(WALL_COLOR);
+
+	for side in Side::ALL.into_iter().filter(|side| settings.controllers[*side as usize].is_some()) {
+		let post_length = arena.half_wall_length(side) - goal_size / 2.0;
+		if post_length <= 0.0 { continue }
+
+		// posts stand behind the line the ball bounces off
+		let wall = match side {
+			Side::Left | Side::Right => arena.right_wall(),
+			Side::Bottom | Side::Top => arena.top_wall(),
+		};
+		let wall_offset = wall + arena.wall_thickness / 2.0;
+		let size = side.axis() * post_length + side.normal().abs() * arena.wall_thickness;
+		let mesh = Mesh2dHandle(meshes.add(Rectangle::from_size(size)));
+
+		for direction in [-1.0, 1.0] {
+			let position = side.normal() * wall_offset + side.axis() * direction * (goal_size + post_length) / 2.0;
+			commands.spawn((
+				GoalPost(side),
+				MaterialMesh2dBundle {
+					mesh: mesh.clone(),
+					material: material.clone(),
+					transform: Transform::from_translation(position.extend(ZLAYER::WALL)),
+					..default()
+				},
+			));
+		}
+	}
+}
+
+// Walls of eliminated sides are solid, their posts go with the paddle
+fn show_goal_posts(
+	paddle_query: Query<(&Side, Has<Eliminated>), With<Paddle>>,
+	mut post_query: Query<(&mut Visibility, &GoalPost)>,
+) {
+	for (mut visibility, post) in &mut post_query {
+		let is_eliminated = paddle_query.iter().any(|(side, is_eliminated)| *side == post.0 && is_eliminated);
+		visibility.set_if_neq(if is_eliminated { Visibility::Hidden } else { Visibility::Inherited });
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::asset::LoadState;
+
+	fn posts(app: &mut App) -> Vec<(Side, Vec2, Visibility)> {
+		let mut query = app.world.query::<(&GoalPost, &Transform, &Visibility)>();
+		query.iter(&app.world).map(|(post, transform, visibility)| (post.0, transform.translation.xy(), *visibility)).collect()
+	}
+
+	#[test]
+	fn goal_posts_line_up_with_the_walls_and_leave_with_their_side() {
+		let arena = Arena { goal_size: Some(100.0), ..default() };
+		let mut app = App::new();
+		app.init_resource::<Assets<Mesh>>()
+			.init_resource::<Assets<ColorMaterial>>()
+			.init_resource::<Settings>()
+			.insert_resource(arena.clone())
+			.add_systems(Update, (update_goal_posts, show_goal_posts).chain());
+		let left = app.world.spawn((Paddle, Side::Left)).id();
+		app.world.spawn((Paddle, Side::Right));
+		app.update();
+
+		let posts_before = posts(&mut app);
+		assert_eq!(posts_before.len(), 4);
+		for (side, position, visibility) in posts_before {
+			// inner edge of the post on the line the ball bounces off
+			let inner_edge = position.dot(side.normal()) - arena.wall_thickness / 2.0;
+			assert_eq!(inner_edge, arena.right_wall(), "{side:?} post at {position}");
+			assert_eq!(visibility, Visibility::Inherited);
+		}
+
+		app.world.entity_mut(left).insert(Eliminated);
+		app.update();
+		for (side, _, visibility) in posts(&mut app) {
+			let expected = if side == Side::Left { Visibility::Hidden } else { Visibility::Inherited };
+			assert_eq!(visibility, expected, "{side:?} post");
+		}
+	}
+
+	#[test]
+	fn tuning_file_loads_through_the_asset_server() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+			.init_asset::<ArenaDefinition>()
+			.register_asset_loader(ArenaLoader)
+			.init_asset::<Tuning>()
+			.register_asset_loader(TuningLoader);
+		let asset_server = app.world.resource::<AssetServer>().clone();
+
+		// the file finds its loader by extension, like the arenas
+		let loader = bevy::tasks::block_on(asset_server.get_path_asset_loader(TUNING_PATH)).unwrap();
+		assert_eq!(loader.asset_type_name(), std::any::type_name::<Tuning>());
+
+		let handle: Handle<Tuning> = asset_server.load(TUNING_PATH);
+
+		for _ in 0..1000 {
+			app.update();
+			match asset_server.load_state(&handle) {
+				LoadState::Loading | LoadState::NotLoaded => std::thread::sleep(std::time::Duration::from_millis(5)),
+				_ => break,
+			}
+		}
+		assert_eq!(asset_server.load_state(&handle), LoadState::Loaded);
+		let tuning = app.world.resource::<Assets<Tuning>>().get(&handle).unwrap();
+		assert_eq!(format!("{tuning:?}"), format!("{:?}", Tuning::default()));
+	}
+}
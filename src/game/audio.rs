@@ -0,0 +1,88 @@
+// Sound of collisions and the volume controls.
+
+// import bevy
+use bevy::prelude::*;
+use bevy::audio::Volume;
+
+// import crate
+use crate::game::physics::{CollisionEvent, PhysicsSet};
+
+// Sounds of what physics reported in this frame
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub struct AudioSet;
+
+const KEYCODE_VOLUME_UP: KeyCode   = KeyCode::F10;
+const KEYCODE_VOLUME_DOWN: KeyCode = KeyCode::F9;
+
+// Resources
+#[derive(Resource, Deref, DerefMut)] pub struct CollisionSound(pub Handle<AudioSource>);
+#[derive(Resource, Deref, DerefMut)] pub struct GlobalVolume(pub Volume);
+
+pub struct AudioPlugin;
+impl Plugin for AudioPlugin {
+	fn build(&self, app: &mut App) {
+		// Events
+		app.add_event::<CollisionEvent>();
+
+		// Resources
+		app.insert_resource(GlobalVolume(Volume::default()));
+
+		// Systems: startup
+		app.add_systems(Startup, audio_setup);
+
+		// Systems: update
+		app.configure_sets(Update, AudioSet.after(PhysicsSet::Collision))
+			.add_systems(Update,
+				(
+				play_collision_sounds.run_if(resource_exists::<CollisionSound>),
+				volume_control,
+				)
+				.in_set(AudioSet)
+				);
+	}
+}
+
+fn audio_setup(
+	mut commands: Commands,
+	asset_server: Res<AssetServer>,
+) {
+	let ball_collision_sound = asset_server.load("embedded://sounds/ball_collision.ogg");
+	commands.insert_resource(CollisionSound(ball_collision_sound));
+}
+
+// Play a sound once per frame if a collision occurred
+fn play_collision_sounds(
+	mut commands: Commands,
+	mut collision_events: EventReader<CollisionEvent>,
+	sound: Res<CollisionSound>,
+	volume: Res<GlobalVolume>,
+) {
+	if collision_events.is_empty() { return }
+	collision_events.clear();
+
+	play_collision_sound(&mut commands, &sound, &volume);
+}
+
+pub fn play_collision_sound(
+	commands: &mut Commands,
+	sound: &CollisionSound,
+	volume: &GlobalVolume,
+) {
+	commands.spawn(AudioBundle {
+		source: sound.0.clone(),
+		settings: PlaybackSettings::DESPAWN.with_volume(volume.0),
+	});
+}
+
+fn volume_control(
+    input: Res<ButtonInput<KeyCode>>,
+	mut volume: ResMut<GlobalVolume>,
+) {
+	let mut delta_volume = 0.0;
+
+	if input.just_pressed(KEYCODE_VOLUME_UP)   { delta_volume =  0.1 }
+	if input.just_pressed(KEYCODE_VOLUME_DOWN) { delta_volume = -0.1 }
+	if delta_volume == 0.0 { return }
+
+	let set_volume = (volume.get() + delta_volume).clamp(0.0, 1.0);
+	volume.0 = Volume::new(set_volume);
+}
@@ -0,0 +1,168 @@
+// Debug overlay, built with --features debug: bounding boxes, velocities, AI intercepts and a panel
+// with the state, timers, ball speeds, FPS and entity counts. Toggled with F12
+
+// import bevy
+use bevy::prelude::*;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::math::bounding::{Aabb2d, BoundingVolume};
+
+// import crate
+use crate::game::{Ball, Collider, Eliminated, MaxSpeed, Paddle, Velocity, ZLAYER};
+use crate::game::ai::{most_threatening_ball, Ai, AiPlan};
+use crate::game::state::{GameplayState, StateTimer};
+use crate::game::ui::{BASIC_TEXT_COLOR, FONT_MEDIUM, GLOBAL_TEXT_SCALE, TEXT_RESOLUTION};
+use crate::{predict_intercept, Arena, Side, BALL_SIZE};
+
+const KEYCODE_DEBUG: KeyCode   = KeyCode::F12;
+const DEBUG_FONT_SIZE: f32     = TEXT_RESOLUTION * 12.0;
+const VELOCITY_SCALE: f32      = 0.25; // arrows show where things are in this many seconds
+const INTERCEPT_RADIUS: f32    = 6.0;
+
+const BALL_BOX_COLOR: Color      = Color::GREEN;
+const COLLIDER_BOX_COLOR: Color  = Color::CYAN;
+const ARENA_BOX_COLOR: Color     = Color::DARK_GREEN;
+const VELOCITY_COLOR: Color      = Color::YELLOW;
+const INTERCEPT_COLOR: Color     = Color::FUCHSIA;
+const AI_TARGET_COLOR: Color     = Color::ORANGE;
+
+#[derive(Component)] struct DebugUi;
+#[derive(Resource, Deref, DerefMut)] struct DebugOverlay(bool);
+
+pub struct DebugPlugin;
+impl Plugin for DebugPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(FrameTimeDiagnosticsPlugin)
+			.insert_resource(DebugOverlay(true))
+			.add_systems(Startup, debug_setup)
+			.add_systems(Update, (
+				toggle_debug_overlay,
+				(draw_bounding_boxes, draw_velocities, draw_ai_intercepts, update_debug_ui)
+					.run_if(|overlay: Res<DebugOverlay>| overlay.0),
+				).chain());
+	}
+}
+
+fn debug_setup(
+	mut commands: Commands,
+	asset_server: Res<AssetServer>,
+	arena: Res<Arena>,
+) {
+	commands.spawn((
+		DebugUi,
+		Text2dBundle {
+			text:
+				Text::from_section("", TextStyle {
+				font: asset_server.load(FONT_MEDIUM),
+				font_size: DEBUG_FONT_SIZE,
+				color: BASIC_TEXT_COLOR })
+				.with_justify(JustifyText::Right),
+			text_anchor: bevy::sprite::Anchor::TopRight,
+			transform:
+				Transform::from_xyz(arena.size.x / 2.0 - 10.0, arena.size.y / 2.0 - 10.0, ZLAYER::TEXT)
+				.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+			..default()
+		}));
+}
+
+fn toggle_debug_overlay(
+	input: Res<ButtonInput<KeyCode>>,
+	mut overlay: ResMut<DebugOverlay>,
+	mut query: Query<&mut Visibility, With<DebugUi>>,
+) {
+	if !input.just_pressed(KEYCODE_DEBUG) { return }
+
+	overlay.0 = !overlay.0;
+	for mut visibility in &mut query {
+		*visibility = match overlay.0 {
+			true  => Visibility::Inherited,
+			false => Visibility::Hidden,
+		};
+	}
+}
+
+fn draw_bounding_boxes(
+	mut gizmos: Gizmos,
+	arena: Res<Arena>,
+	ball_query: Query<&Transform, With<Ball>>,
+	collider_query: Query<(&Transform, &Collider), Without<Eliminated>>,
+) {
+	let walls = Vec2::new(arena.left_wall() + arena.right_wall(), arena.bottom_wall() + arena.top_wall()) / 2.0;
+	let size = Vec2::new(arena.right_wall() - arena.left_wall(), arena.top_wall() - arena.bottom_wall());
+	gizmos.rect_2d(walls, 0.0, size, ARENA_BOX_COLOR);
+
+	for transform in &ball_query {
+		let aabb = Aabb2d::new(transform.translation.xy(), BALL_SIZE / 2.0);
+		gizmos.rect_2d(aabb.center(), 0.0, aabb.half_size() * 2.0, BALL_BOX_COLOR);
+	}
+	for (transform, collider) in &collider_query {
+		let aabb = Aabb2d::new(transform.translation.xy(), collider.0 * transform.scale.xy() / 2.0);
+		gizmos.rect_2d(aabb.center(), 0.0, aabb.half_size() * 2.0, COLLIDER_BOX_COLOR);
+	}
+}
+
+fn draw_velocities(
+	mut gizmos: Gizmos,
+	query: Query<(&Transform, &Velocity)>,
+) {
+	for (transform, velocity) in &query {
+		if velocity.0 == Vec2::ZERO { continue }
+		let start = transform.translation.xy();
+		gizmos.arrow_2d(start, start + velocity.0 * VELOCITY_SCALE, VELOCITY_COLOR);
+	}
+}
+
+// Where the ball reaches every AI paddle and where the AI heads for
+fn draw_ai_intercepts(
+	mut gizmos: Gizmos,
+	arena: Res<Arena>,
+	paddle_query: Query<(&Transform, &Collider, &Side, Option<&AiPlan>), (With<Paddle>, With<Ai>, Without<Eliminated>)>,
+	ball_query: Query<(&Transform, &Velocity), (With<Ball>, Without<Paddle>)>,
+) {
+	for (transform, collider, side, plan) in &paddle_query {
+		let paddle = transform.translation.xy();
+		let across = paddle * side.normal().abs(); // the line the paddle moves on
+
+		let balls = ball_query.iter().map(|(transform, velocity)| (transform.translation.xy(), velocity.0));
+		if let Some((position, velocity)) = most_threatening_ball(*side, paddle, balls) {
+			if let Some((along, _)) = predict_intercept(*side, position, velocity, collider.0 * transform.scale.xy(), &arena) {
+				let intercept = across + side.axis() * along;
+				gizmos.line_2d(position, intercept, INTERCEPT_COLOR);
+				gizmos.circle_2d(intercept, INTERCEPT_RADIUS, INTERCEPT_COLOR);
+			}
+		}
+		if let Some(target) = plan.and_then(|plan| plan.target) {
+			gizmos.line_2d(paddle, across + side.axis() * target, AI_TARGET_COLOR);
+		}
+	}
+}
+
+fn update_debug_ui(
+	state: Res<State<GameplayState>>,
+	timer: Res<StateTimer>,
+	time: Res<Time<Virtual>>,
+	diagnostics: Res<DiagnosticsStore>,
+	ball_query: Query<&MaxSpeed, With<Ball>>,
+	paddle_query: Query<(), With<Paddle>>,
+	collider_query: Query<(), With<Collider>>,
+	entity_query: Query<()>,
+	mut query: Query<&mut Text, With<DebugUi>>,
+) {
+	let fps = diagnostics
+		.get(&FrameTimeDiagnosticsPlugin::FPS)
+		.and_then(|fps| fps.smoothed())
+		.unwrap_or(0.0);
+	let max_speeds: Vec<String> = ball_query.iter().map(|max_speed| format!("{:.0}", max_speed.0)).collect();
+	let speed = match time.is_paused() {
+		true  => "paused".to_string(),
+		false => format!("{}x", time.relative_speed()),
+	};
+
+	for mut text in &mut query {
+		let Some(section) = text.sections.first_mut() else { continue };
+		section.value = format!(
+			"{:?}\nTimer {:.2}s\nTime {speed}\nMax speed {}\nFPS {fps:.0}\nEntities {}\nBalls {}  Paddles {}  Colliders {}",
+			state.get(), timer.remaining_secs(), max_speeds.join(" "),
+			entity_query.iter().count(), ball_query.iter().count(), paddle_query.iter().count(), collider_query.iter().count(),
+		);
+	}
+}
@@ -0,0 +1,367 @@
+// Keyboard, mouse and touch controls: paddles of the players, answers to prompts and,
+// built with --features debug, the flow of virtual time.
+
+// import std
+#[cfg(feature = "debug")]
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+#[cfg(feature = "debug")]
+use bevy::time::TimeSystem;
+use bevy::input::touch::Touches;
+use bevy::window::PrimaryWindow;
+
+// import crate
+use crate::game::{init_shared_resources, Intent, NetworkView, Paddle, Velocity};
+use crate::game::state::GameplayState;
+use crate::simulation::PaddleInput;
+#[cfg(feature = "debug")]
+use crate::simulation::TICK_RATE;
+use crate::{PaddleMotion, Side};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum ControlScheme { Any, Arrows, Wasd, Ijkl, Numpad, Mouse, Touch }
+
+// Keys, cursor and touches read into intents and events, before the AI and physics
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub struct InputSet;
+
+#[cfg(feature = "debug")]
+pub const TIME_SPEEDS: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 4.0]; // of virtual time, F7/F8
+
+pub const KEYCODES_ACCEPT: [KeyCode; 2]       = [KeyCode::Space, KeyCode::Enter];
+pub const KEYCODES_PADDLE_RIGHT: [KeyCode; 4] = [KeyCode::ArrowUp,  KeyCode::ArrowRight, KeyCode::KeyW, KeyCode::KeyD];
+pub const KEYCODES_PADDLE_LEFT: [KeyCode; 4]  = [KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::KeyS, KeyCode::KeyA];
+const KEYCODES_ARROWS: [[KeyCode; 2]; 2]      = [[KeyCode::ArrowUp, KeyCode::ArrowRight], [KeyCode::ArrowDown, KeyCode::ArrowLeft]];
+const KEYCODES_WASD: [[KeyCode; 2]; 2]        = [[KeyCode::KeyW, KeyCode::KeyD], [KeyCode::KeyS, KeyCode::KeyA]];
+const KEYCODES_IJKL: [[KeyCode; 2]; 2]        = [[KeyCode::KeyI, KeyCode::KeyL], [KeyCode::KeyK, KeyCode::KeyJ]];
+const KEYCODES_NUMPAD: [[KeyCode; 2]; 2]      = [[KeyCode::Numpad8, KeyCode::Numpad6], [KeyCode::Numpad2, KeyCode::Numpad4]];
+#[cfg(feature = "debug")] pub const KEYCODE_PAUSE: KeyCode  = KeyCode::F5;
+#[cfg(feature = "debug")] pub const KEYCODE_STEP: KeyCode   = KeyCode::F6;
+#[cfg(feature = "debug")] pub const KEYCODE_SLOWER: KeyCode = KeyCode::F7;
+#[cfg(feature = "debug")] pub const KEYCODE_FASTER: KeyCode = KeyCode::F8;
+
+// Components
+#[derive(Component, Deref, DerefMut)] pub struct Player(pub ControlScheme);
+
+// Events
+#[derive(Event)] pub struct AcceptEvent; // answer to the instructions and the game over screen
+
+// Resources
+#[cfg(feature = "debug")]
+#[derive(Resource, Default, Deref, DerefMut)] pub struct FrameStep(bool); // one tick requested while paused
+
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Events
+		app.add_event::<AcceptEvent>();
+
+		// Systems: update, the networked game reads the keys itself and runs on the clock of the peers
+		app.configure_sets(Update, InputSet)
+			.add_systems(Update,
+				(
+				send_accept,
+				(player_control, pointer_control).run_if(not(in_state(GameplayState::Startup))),
+				)
+				.in_set(InputSet)
+				.run_if(not(resource_exists::<NetworkView>))
+				);
+
+		// Systems: dev controls of virtual time
+		#[cfg(feature = "debug")]
+		app.init_resource::<FrameStep>()
+			.add_systems(First, step_virtual_time.after(TimeSystem))
+			.add_systems(Update, control_time.in_set(InputSet).run_if(not(resource_exists::<NetworkView>)));
+	}
+}
+
+impl ControlScheme {
+	// Keys moving the paddle up/right and down/left, pointer schemes fall back to any keys in networked play
+	pub fn keys(self) -> (&'static [KeyCode], &'static [KeyCode]) {
+		match self {
+			ControlScheme::Any | ControlScheme::Mouse | ControlScheme::Touch => (&KEYCODES_PADDLE_RIGHT, &KEYCODES_PADDLE_LEFT),
+			ControlScheme::Arrows => (&KEYCODES_ARROWS[0], &KEYCODES_ARROWS[1]),
+			ControlScheme::Wasd   => (&KEYCODES_WASD[0], &KEYCODES_WASD[1]),
+			ControlScheme::Ijkl   => (&KEYCODES_IJKL[0], &KEYCODES_IJKL[1]),
+			ControlScheme::Numpad => (&KEYCODES_NUMPAD[0], &KEYCODES_NUMPAD[1]),
+		}
+	}
+
+	pub fn is_pointer(self) -> bool {
+		matches!(self, ControlScheme::Mouse | ControlScheme::Touch)
+	}
+}
+
+// Touching the screen or clicking answers too, for tablets without a keyboard
+fn send_accept(
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	mouse_input: Option<Res<ButtonInput<MouseButton>>>,
+	touches: Option<Res<Touches>>,
+	mut accept_events: EventWriter<AcceptEvent>,
+) {
+	let is_clicked = mouse_input.is_some_and(|mouse_input| mouse_input.just_pressed(MouseButton::Left));
+	let is_touched = touches.is_some_and(|touches| touches.any_just_pressed());
+	if keyboard_input.any_just_pressed(KEYCODES_ACCEPT) || is_clicked || is_touched
+	{
+		accept_events.send(AcceptEvent);
+	}
+}
+
+fn player_control(
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	mut query: Query<(&mut Intent, &Player), With<Paddle>>,
+) {
+	for (mut intent, player) in &mut query {
+		if player.is_pointer() { continue }
+
+		let (keys_positive, keys_negative) = player.keys();
+		let is_positive = keyboard_input.any_pressed(keys_positive.iter().copied());
+		let is_negative = keyboard_input.any_pressed(keys_negative.iter().copied());
+		intent.0 = f32::from(is_positive) - f32::from(is_negative);
+	}
+}
+
+// Paddles follow the cursor or a touch on their half of the screen, within the limits of their motion
+fn pointer_control(
+	window_query: Query<&Window, With<PrimaryWindow>>,
+	camera_query: Query<(&Camera, &GlobalTransform)>,
+	touches: Option<Res<Touches>>,
+	mut query: Query<(&Transform, &Velocity, &mut Intent, &PaddleMotion, &Side, &Player), With<Paddle>>,
+) {
+	if query.iter().all(|(.., player)| !player.is_pointer()) { return }
+
+	// Window positions into the world through the projection of the camera
+	let (Ok(window), Ok((camera, camera_transform))) = (window_query.get_single(), camera_query.get_single()) else { return };
+	let to_world = |position: Vec2| camera.viewport_to_world_2d(camera_transform, position);
+	let cursor = window.cursor_position().and_then(to_world);
+	let touched: Vec<Vec2> = touches.as_deref().into_iter()
+		.flat_map(Touches::iter)
+		.filter_map(|touch| to_world(touch.position()))
+		.collect();
+
+	for (transform, velocity, mut intent, motion, side, player) in &mut query {
+		let target = match player.0 {
+			ControlScheme::Mouse => cursor.map(|cursor| cursor.dot(side.axis())),
+			ControlScheme::Touch => touch_target(*side, touched.iter().copied()),
+			_                    => continue,
+		};
+		let Some(target) = target else {
+			intent.0 = 0.0;
+			continue;
+		};
+
+		let position = transform.translation.xy().dot(side.axis());
+		intent.0 = motion.intent_towards(position, velocity.dot(side.axis()), target);
+	}
+}
+
+// Position along the side axis of the first touch on the half of the arena of the side
+pub fn touch_target(side: Side, mut touches: impl Iterator<Item = Vec2>) -> Option<f32> {
+	touches
+		.find(|touch| touch.dot(side.normal()) > 0.0)
+		.map(|touch| touch.dot(side.axis()))
+}
+
+// Buttons held for the paddle of this player
+pub fn read_paddle_input(
+	keyboard_input: &ButtonInput<KeyCode>,
+	player_query: &Query<&Player>,
+) -> PaddleInput {
+	let scheme = player_query.iter().next().map_or(ControlScheme::Any, |player| player.0);
+	let (keys_positive, keys_negative) = scheme.keys();
+	PaddleInput::new(
+		keyboard_input.any_pressed(keys_positive.iter().copied()),
+		keyboard_input.any_pressed(keys_negative.iter().copied()),
+		keyboard_input.any_pressed(KEYCODES_ACCEPT),
+	)
+}
+
+// Pause, single ticks and speed of virtual time, which drives physics and gameplay timers
+#[cfg(feature = "debug")]
+fn control_time(
+	input: Res<ButtonInput<KeyCode>>,
+	mut time: ResMut<Time<Virtual>>,
+	mut step: ResMut<FrameStep>,
+) {
+	if input.just_pressed(KEYCODE_PAUSE) {
+		match time.is_paused() {
+			true  => time.unpause(),
+			false => time.pause(),
+		}
+	}
+	if input.just_pressed(KEYCODE_STEP) && time.is_paused() {
+		step.0 = true;
+	}
+
+	let speed = time.relative_speed();
+	let index = TIME_SPEEDS.iter().position(|known| *known >= speed).unwrap_or(TIME_SPEEDS.len() - 1);
+	let new_index = match (input.just_pressed(KEYCODE_SLOWER), input.just_pressed(KEYCODE_FASTER)) {
+		(true, false) => index.saturating_sub(1),
+		(false, true) => (index + 1).min(TIME_SPEEDS.len() - 1),
+		_             => return,
+	};
+	time.set_relative_speed(TIME_SPEEDS[new_index]);
+}
+
+// Advances paused virtual time by one tick, after it was updated from the real clock
+#[cfg(feature = "debug")]
+fn step_virtual_time(
+	mut step: ResMut<FrameStep>,
+	mut virtual_time: ResMut<Time<Virtual>>,
+	mut time: ResMut<Time>,
+) {
+	if !step.0 { return }
+	step.0 = false;
+
+	if virtual_time.is_paused() {
+		virtual_time.advance_by(Duration::from_secs_f64(1.0 / TICK_RATE));
+		*time = virtual_time.as_generic();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::render::camera::{camera_system, ManualTextureViews};
+	use bevy::window::{WindowCreated, WindowResized, WindowResolution, WindowScaleFactorChanged};
+	use crate::game::tests::{press, start_match, test_app};
+	use crate::game::{Controller, Settings};
+	use crate::{Arena, PROJECTION_MARGIN};
+
+	#[test]
+	fn touches_steer_the_paddle_of_their_half() {
+		let settings = Settings::from_args(["--left", "touch", "--right", "touch"].into_iter().map(String::from));
+		assert_eq!(settings.controllers[..2], [Some(Controller::Human(ControlScheme::Touch)); 2]);
+
+		let touches = [Vec2::new(-200.0, 50.0), Vec2::new(150.0, -80.0)];
+		assert_eq!(touch_target(Side::Left, touches.into_iter()), Some(50.0));
+		assert_eq!(touch_target(Side::Right, touches.into_iter()), Some(-80.0));
+		assert_eq!(touch_target(Side::Right, touches[..1].iter().copied()), None);
+		assert_eq!(touch_target(Side::Top, touches.into_iter()), Some(-200.0));
+	}
+
+	#[test]
+	fn pointer_players_without_a_window_stand_still() {
+		let mut app = test_app();
+		let mut query = app.world.query::<&mut Player>();
+		for mut player in query.iter_mut(&mut app.world) {
+			player.0 = ControlScheme::Mouse;
+		}
+		start_match(&mut app);
+
+		press(&mut app, KeyCode::ArrowUp);
+		let mut query = app.world.query_filtered::<&Intent, With<Player>>();
+		assert_eq!(query.single(&app.world).0, 0.0);
+	}
+
+	#[test]
+	fn mouse_steers_towards_the_cursor_in_the_world() {
+		let mut app = test_app();
+		// camera of the game computed like the render plugin does, without rendering
+		app.add_event::<WindowCreated>()
+			.add_event::<WindowResized>()
+			.add_event::<WindowScaleFactorChanged>()
+			.add_event::<AssetEvent<Image>>()
+			.init_resource::<Assets<Image>>()
+			.init_resource::<ManualTextureViews>()
+			.add_systems(PostUpdate, camera_system::<OrthographicProjection>);
+		let arena = app.world.resource::<Arena>().clone();
+		app.world.spawn((Window { resolution: WindowResolution::new(800.0, 600.0), ..default() }, PrimaryWindow));
+		app.world.spawn(Camera2dBundle {
+			projection: OrthographicProjection { scaling_mode: arena.projection(), ..Camera2dBundle::default().projection },
+			..default()
+		});
+		let mut query = app.world.query::<&mut Player>();
+		query.single_mut(&mut app.world).0 = ControlScheme::Mouse;
+		start_match(&mut app);
+
+		// the arena fills the window, the cursor above the middle is that much higher in the world
+		let scale = ((arena.size.x + PROJECTION_MARGIN) / 800.0).max((arena.size.y + PROJECTION_MARGIN) / 600.0);
+		for (cursor, world_y) in [(Vec2::new(400.0, 100.0), 200.0 * scale), (Vec2::new(700.0, 550.0), -250.0 * scale)] {
+			let mut query = app.world.query::<&mut Window>();
+			query.single_mut(&mut app.world).set_cursor_position(Some(cursor));
+			app.update();
+
+			let mut query = app.world.query::<(&Camera, &GlobalTransform)>();
+			let (camera, camera_transform) = query.single(&app.world);
+			let converted = camera.viewport_to_world_2d(camera_transform, cursor).unwrap();
+			assert!((converted.y - world_y).abs() < 0.01, "cursor at {cursor} is {converted} in the world");
+
+			let mut query = app.world.query_filtered::<(&Transform, &Velocity, &Intent, &PaddleMotion), With<Player>>();
+			let (transform, velocity, intent, motion) = query.single(&app.world);
+			let expected = motion.intent_towards(transform.translation.y, velocity.y, converted.y);
+			assert_eq!(intent.0.signum(), (converted.y - transform.translation.y).signum());
+			assert!((intent.0 - expected).abs() < 0.05, "intent {} instead of {expected}", intent.0);
+		}
+	}
+
+	#[cfg(feature = "debug")]
+	mod time_controls {
+		use super::*;
+		use crate::game::tests::{ball, press_accept, run_until, state, FRAME, MAX_FRAMES};
+		use crate::game::state::{GameplayState, StateTimer};
+		use crate::simulation::TICK_RATE;
+
+		#[test]
+		fn pause_freezes_timers_and_physics() {
+			let mut app = test_app();
+			press_accept(&mut app);
+			assert!(run_until(&mut app, GameplayState::Start));
+
+			press(&mut app, KEYCODE_PAUSE);
+			let remaining = app.world.resource::<StateTimer>().remaining();
+			for _ in 0..MAX_FRAMES { app.update() }
+			assert_eq!(state(&app), GameplayState::Start);
+			assert_eq!(app.world.resource::<StateTimer>().remaining(), remaining);
+
+			press(&mut app, KEYCODE_PAUSE);
+			assert!(run_until(&mut app, GameplayState::Active));
+			app.update();
+
+			press(&mut app, KEYCODE_PAUSE);
+			let (position, ..) = ball(&mut app);
+			for _ in 0..10 { app.update() }
+			assert_eq!(ball(&mut app).0, position);
+		}
+
+		#[test]
+		fn step_advances_one_tick_while_paused() {
+			let mut app = test_app();
+			start_match(&mut app);
+			app.update();
+			press(&mut app, KEYCODE_PAUSE);
+			app.update();
+
+			let (position, velocity, ..) = ball(&mut app);
+			press(&mut app, KEYCODE_STEP);
+			for _ in 0..10 { app.update() }
+			let moved = ball(&mut app).0 - position;
+			assert!((moved.xy() - velocity / TICK_RATE as f32).length() < 0.01, "moved {moved}");
+		}
+
+		#[test]
+		fn speed_scales_physics() {
+			let mut app = test_app();
+			start_match(&mut app);
+			app.update();
+
+			let (position, velocity, ..) = ball(&mut app);
+			app.update();
+			let normal = ball(&mut app).0 - position;
+			assert!((normal.xy() - velocity * FRAME.as_secs_f32()).length() < 0.01, "moved {normal}");
+
+			press(&mut app, KEYCODE_SLOWER);
+			press(&mut app, KEYCODE_SLOWER);
+			assert_eq!(app.world.resource::<Time<Virtual>>().relative_speed(), 0.25);
+			let (position, velocity, ..) = ball(&mut app);
+			app.update();
+			let slow = ball(&mut app).0 - position;
+			assert!((slow.xy() - velocity * FRAME.as_secs_f32() * 0.25).length() < 0.01, "moved {slow}");
+
+			for _ in 0..TIME_SPEEDS.len() { press(&mut app, KEYCODE_FASTER) }
+			assert_eq!(app.world.resource::<Time<Virtual>>().relative_speed(), 4.0);
+		}
+	}
+}
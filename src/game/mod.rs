@@ -0,0 +1,590 @@
+// The game as Bevy plugins: states, physics, AI, input, UI, audio, arenas and networked play.
+// Headless builds and tests pick the plugins they need, the game client adds all of them and
+// spawns the rendered world. Plugins order themselves against each other through their SystemSets.
+
+// Bevy systems take many parameters and queries with nested filters by design
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::core_pipeline::bloom::{BloomSettings, BloomPrefilterSettings, BloomCompositeMode};
+
+// import custom
+use serde::Deserialize;
+
+// import crate
+use crate::{Arena, GameMode, PaddleMotion, Side};
+use crate::{BALL_DELTA_SPEED, BALL_STARTING_SPEED, NEXT_SET_DELAY, PADDLE_FRICTION, PLAYER_ACCELERATION, PLAYER_DECELERATION, PLAYER_MAX_SPEED, PADDLE_SIZE, START_DELAY};
+use crate::bot::DEFAULT_BOT_TIMEOUT;
+use crate::protocol::Snapshot;
+
+// rules of the match, shared with the headless simulation
+pub use crate::Scoreboard;
+
+pub mod ai;
+pub mod arena;
+pub mod audio;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod input;
+pub mod network;
+pub mod physics;
+pub mod state;
+pub mod ui;
+
+use input::ControlScheme;
+use physics::PowerUps;
+
+#[non_exhaustive]
+#[allow(clippy::upper_case_acronyms)]
+pub struct ZLAYER;
+impl ZLAYER {
+	pub const FRAME: f32  = 0.0;
+	pub const WALL: f32   = 0.25;
+	pub const SCORE: f32  = 0.5;
+	pub const TEXT: f32   = 1.0;
+	pub const MAIN: f32   = 2.0;
+	pub const BALL: f32   = 3.0;
+	pub const CAMERA: f32 = 4.0;
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum Controller { Human(ControlScheme), Ai, OffensiveAi, Remote, Bot, Neural }
+
+pub const BALL_STARTING_POSITION: Vec3 = Vec3::new(0.0, 0.0, ZLAYER::BALL);
+
+pub const RED_COLOR: Color        = Color::rgb(2.0, 0.0, 0.0);
+pub const GOLD_COLOR: Color       = Color::rgb(2.0, 1.68, 0.0);
+pub const BACKGROUND_COLOR: Color = Color::BLACK;
+
+const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:7777";
+pub const DEFAULT_INPUT_DELAY: u32 = 2;
+
+// Components
+#[derive(Component)] pub struct Paddle;
+#[derive(Component)] pub struct Ball;
+#[derive(Component)] pub struct ExtraBall;
+#[derive(Component, Deref, DerefMut)] pub struct Velocity(pub Vec2);
+#[derive(Component, Deref, DerefMut)] pub struct MaxSpeed(pub f32);
+#[derive(Component, Default, Deref, DerefMut)] pub struct Intent(pub f32); // share of max speed a controller asks for along the side axis, -1 to 1
+#[derive(Component, Deref, DerefMut)] pub struct Collider(pub Vec2);
+#[derive(Component)] pub struct Eliminated;
+#[derive(Component, Deref, DerefMut)] pub struct LastTouch(pub Option<Entity>);
+
+// Bundles
+#[derive(Bundle)] pub struct PaddleBundle {
+	pub paddle: Paddle,
+	pub side: Side,
+	pub collider: Collider,
+	pub velocity: Velocity,
+	pub max_speed: MaxSpeed,
+	pub intent: Intent,
+	pub motion: PaddleMotion,
+	pub power_ups: PowerUps,
+}
+impl PaddleBundle {
+	pub fn new(side: Side, size: Vec2, max_speed: f32, motion: PaddleMotion) -> Self {
+		Self {
+			paddle: Paddle,
+			side,
+			collider: Collider(size),
+			velocity: Velocity(Vec2::ZERO),
+			max_speed: MaxSpeed(max_speed),
+			intent: Intent(0.0),
+			motion,
+			power_ups: PowerUps::default(),
+		}
+	}
+}
+
+#[derive(Bundle)] pub struct BallBundle {
+	pub ball: Ball,
+	pub velocity: Velocity,
+	pub max_speed: MaxSpeed,
+	pub last_touch: LastTouch,
+}
+impl BallBundle {
+	pub fn new(max_speed: f32) -> Self {
+		Self {
+			ball: Ball,
+			velocity: Velocity(Vec2::ZERO),
+			max_speed: MaxSpeed(max_speed),
+			last_touch: LastTouch(None),
+		}
+	}
+}
+
+// Resources
+#[derive(Resource, Default, Deref, DerefMut)] pub struct NetworkView(pub Snapshot); // state to render in networked play
+#[derive(Resource, Debug, Clone)] pub struct Settings {
+	pub mode: GameMode,
+	pub controllers: [Option<Controller>; 4], // indexed by Side
+	pub multiball: bool,
+	pub power_ups: bool,
+	pub arena: Option<String>, // path to *.arena.ron in assets
+	pub goal_size: Option<f32>,
+	pub network: Option<NetworkSettings>,
+	pub server: Option<String>, // address of a dedicated server
+	pub is_spectator: bool,
+	pub is_browsing: bool, // pick a server from the lobby
+	pub bots: [Option<String>; 4], // programs of Controller::Bot paddles, indexed by Side
+	pub bot_timeout: Duration,
+	pub networks: [Option<String>; 4], // weights of Controller::Neural paddles, indexed by Side
+	pub adaptive_ai: Option<f32>, // target win probability of the AI per point
+}
+#[derive(Debug, Clone)] pub struct NetworkSettings {
+	pub bind: String,
+	pub peer: String,
+	pub side: Side, // paddle controlled by this peer
+	pub input_delay: u32, // ticks
+}
+
+// Feel of local matches, reloaded with the file; networked matches keep the built-in values
+#[derive(Asset, Resource, TypePath, Deserialize, Debug, Clone)] pub struct Tuning {
+	pub ball_starting_speed: f32,
+	pub ball_delta_speed: f32,    // added to the max speed of the ball on every collision
+	pub paddle_size: (f32, f32),  // of left and right paddles, turned for bottom and top ones
+	pub player_acceleration: f32, // of every paddle, speeding up
+	pub player_deceleration: f32, // braking and turning around
+	pub paddle_friction: f32,     // slowing down without input
+	pub player_max_speed: f32,
+	pub start_delay: f32,         // seconds
+	pub next_set_delay: f32,
+	pub bloom: BloomTuning,
+}
+#[derive(Deserialize, Debug, Clone)] pub struct BloomTuning {
+	pub intensity: f32,
+	pub low_frequency_boost: f32,
+	pub low_frequency_boost_curvature: f32,
+	pub high_pass_frequency: f32,
+	pub threshold: f32,
+	pub threshold_softness: f32,
+}
+
+impl Default for Tuning {
+	fn default() -> Self {
+		Self {
+			ball_starting_speed: BALL_STARTING_SPEED,
+			ball_delta_speed: BALL_DELTA_SPEED,
+			paddle_size: (PADDLE_SIZE.x, PADDLE_SIZE.y),
+			player_acceleration: PLAYER_ACCELERATION,
+			player_deceleration: PLAYER_DECELERATION,
+			paddle_friction: PADDLE_FRICTION,
+			player_max_speed: PLAYER_MAX_SPEED,
+			start_delay: START_DELAY.as_secs_f32(),
+			next_set_delay: NEXT_SET_DELAY.as_secs_f32(),
+			bloom: BloomTuning {
+				intensity: 0.15,
+				low_frequency_boost: 0.7,
+				low_frequency_boost_curvature: 0.95,
+				high_pass_frequency: 1.0,
+				threshold: 0.6,
+				threshold_softness: 0.2,
+			},
+		}
+	}
+}
+
+impl Tuning {
+	pub fn paddle_size(&self, side: Side) -> Vec2 {
+		let size = Vec2::new(self.paddle_size.0, self.paddle_size.1);
+		match side {
+			Side::Left | Side::Right => size,
+			Side::Bottom | Side::Top => Vec2::new(size.y, size.x),
+		}
+	}
+
+	pub fn paddle_motion(&self) -> PaddleMotion {
+		PaddleMotion {
+			acceleration: self.player_acceleration,
+			deceleration: self.player_deceleration,
+			friction: self.paddle_friction,
+		}
+	}
+
+	// Values the game can run with, the loader rejects files that break it
+	pub fn validate(&self) -> Result<(), String> {
+		let positive = [
+			("ball_starting_speed", self.ball_starting_speed),
+			("paddle_size", self.paddle_size.0),
+			("paddle_size", self.paddle_size.1),
+			("player_acceleration", self.player_acceleration),
+			("player_deceleration", self.player_deceleration),
+			("player_max_speed", self.player_max_speed),
+		];
+		let non_negative = [
+			("ball_delta_speed", self.ball_delta_speed),
+			("paddle_friction", self.paddle_friction),
+			("start_delay", self.start_delay),
+			("next_set_delay", self.next_set_delay),
+		];
+		let bloom = [
+			("bloom.intensity", self.bloom.intensity),
+			("bloom.low_frequency_boost", self.bloom.low_frequency_boost),
+			("bloom.low_frequency_boost_curvature", self.bloom.low_frequency_boost_curvature),
+			("bloom.high_pass_frequency", self.bloom.high_pass_frequency),
+			("bloom.threshold", self.bloom.threshold),
+			("bloom.threshold_softness", self.bloom.threshold_softness),
+		];
+
+		if let Some((name, value)) = positive.iter().chain(&non_negative).chain(&bloom).find(|(_, value)| !value.is_finite()) {
+			return Err(format!("{name} is {value}, expected a number"));
+		}
+		if let Some((name, value)) = positive.iter().find(|(_, value)| *value <= 0.0) {
+			return Err(format!("{name} is {value}, expected more than 0"));
+		}
+		if let Some((name, value)) = non_negative.iter().find(|(_, value)| *value < 0.0) {
+			return Err(format!("{name} is {value}, expected at least 0"));
+		}
+		Ok(())
+	}
+}
+
+impl BloomTuning {
+	pub fn settings(&self) -> BloomSettings {
+		BloomSettings {
+			intensity: self.intensity,
+			low_frequency_boost: self.low_frequency_boost,
+			low_frequency_boost_curvature: self.low_frequency_boost_curvature,
+			high_pass_frequency: self.high_pass_frequency,
+			prefilter_settings: BloomPrefilterSettings {
+				threshold: self.threshold,
+				threshold_softness: self.threshold_softness,
+			},
+			composite_mode: BloomCompositeMode::Additive,
+		}
+	}
+}
+
+impl Controller {
+	fn parse(value: &str) -> Option<Self> {
+		match value {
+			"ai"     => Some(Controller::Ai),
+			"offensive" => Some(Controller::OffensiveAi),
+			"human"  => Some(Controller::Human(ControlScheme::Any)),
+			"arrows" => Some(Controller::Human(ControlScheme::Arrows)),
+			"wasd"   => Some(Controller::Human(ControlScheme::Wasd)),
+			"ijkl"   => Some(Controller::Human(ControlScheme::Ijkl)),
+			"numpad" => Some(Controller::Human(ControlScheme::Numpad)),
+			"mouse"  => Some(Controller::Human(ControlScheme::Mouse)),
+			"touch"  => Some(Controller::Human(ControlScheme::Touch)),
+			_        => None,
+		}
+	}
+}
+
+// A local versus match of the AI against the player
+impl Default for Settings {
+	fn default() -> Self {
+		Self::from_args(std::iter::empty())
+	}
+}
+
+impl Settings {
+	// Usage: pong [--four-players] [--multiball] [--power-ups] [--arena <path>] [--goal-size <size>]
+	//             [--left|--right|--bottom|--top ai|human|arrows|wasd|ijkl|numpad|mouse|touch|bot:<command>|tcp:<address>]
+	//             [--bot-timeout <ms>]
+	//             [--peer <address> [--bind <address>] [--side left|right] [--input-delay <ticks>]]
+	//             [--server <address>] [--spectate <address>] [--browse]
+	pub fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+		let mut settings = Settings {
+			mode: GameMode::Versus,
+			controllers: [Some(Controller::Ai), Some(Controller::Human(ControlScheme::Any)), None, None],
+			multiball: false,
+			power_ups: false,
+			arena: None,
+			goal_size: None,
+			network: None,
+			server: None,
+			is_spectator: false,
+			is_browsing: false,
+			bots: [None, None, None, None],
+			bot_timeout: DEFAULT_BOT_TIMEOUT,
+			networks: [None, None, None, None],
+			adaptive_ai: None,
+		};
+		let mut overrides = Vec::new();
+		let mut network = NetworkSettings {
+			bind: DEFAULT_BIND_ADDRESS.into(),
+			peer: String::new(),
+			side: Side::Left,
+			input_delay: DEFAULT_INPUT_DELAY,
+		};
+
+		while let Some(arg) = args.next() {
+			let side = match arg.as_str() {
+				"--four-players" => { settings.mode = GameMode::FourPlayers; continue }
+				"--multiball"    => { settings.multiball = true; continue }
+				"--power-ups"    => { settings.power_ups = true; continue }
+				"--arena"        => { settings.arena = args.next(); continue }
+				"--goal-size"    => {
+					settings.goal_size = args.next().and_then(|value| value.parse().ok());
+					if settings.goal_size.is_none() { eprintln!("Expected size after {arg}") }
+					continue
+				}
+				"--server" => { settings.server = args.next(); continue }
+				"--bot-timeout" => {
+					match args.next().and_then(|value| value.parse().ok()) {
+						Some(millis) => settings.bot_timeout = Duration::from_millis(millis),
+						None => eprintln!("Expected milliseconds after {arg}"),
+					}
+					continue
+				}
+				"--browse" => { settings.is_browsing = true; continue }
+				"--adaptive-ai" => {
+					match args.next().and_then(|value| value.parse::<f32>().ok()) {
+						Some(target) if (0.0..=1.0).contains(&target) => settings.adaptive_ai = Some(target),
+						_ => eprintln!("Expected probability within 0..1 after {arg}"),
+					}
+					continue
+				}
+				"--spectate" => {
+					settings.server = args.next();
+					settings.is_spectator = true;
+					continue
+				}
+				"--peer" => { network.peer = args.next().unwrap_or_default(); continue }
+				"--bind" => { network.bind = args.next().unwrap_or_default(); continue }
+				"--side" => {
+					match args.next().as_deref() {
+						Some("left")  => network.side = Side::Left,
+						Some("right") => network.side = Side::Right,
+						_ => eprintln!("Expected left or right after {arg}"),
+					}
+					continue
+				}
+				"--input-delay" => {
+					match args.next().and_then(|value| value.parse().ok()) {
+						Some(ticks) => network.input_delay = ticks,
+						None => eprintln!("Expected ticks after {arg}"),
+					}
+					continue
+				}
+				"--left"   => Side::Left,
+				"--right"  => Side::Right,
+				"--bottom" => Side::Bottom,
+				"--top"    => Side::Top,
+				_ => { eprintln!("Unknown argument: {arg}"); continue }
+			};
+			let value = args.next().unwrap_or_default();
+			if let Some(spec) = value.strip_prefix("bot:").or(value.starts_with("tcp:").then_some(value.as_str())) {
+				settings.bots[side as usize] = Some(spec.to_string());
+				overrides.push((side, Controller::Bot));
+				continue;
+			}
+			if let Some(spec) = value.strip_prefix("nn:") {
+				settings.networks[side as usize] = Some(spec.to_string());
+				overrides.push((side, Controller::Neural));
+				continue;
+			}
+			match Controller::parse(&value) {
+				Some(controller) => overrides.push((side, controller)),
+				None => eprintln!("Expected controller after {arg}"),
+			}
+		}
+
+		if settings.mode == GameMode::FourPlayers {
+			settings.controllers[Side::Bottom as usize] = Some(Controller::Ai);
+			settings.controllers[Side::Top as usize]    = Some(Controller::Ai);
+		}
+		for (side, controller) in overrides {
+			if settings.mode == GameMode::Versus && matches!(side, Side::Bottom | Side::Top) {
+				eprintln!("{} paddle requires --four-players", side.name());
+				continue;
+			}
+			settings.controllers[side as usize] = Some(controller);
+		}
+
+		// Networked play only simulates the classic versus match
+		if !network.peer.is_empty() || settings.server.is_some() || settings.is_browsing {
+			if settings.mode != GameMode::Versus || settings.multiball || settings.power_ups || settings.arena.is_some() {
+				eprintln!("Networked play ignores --four-players, --multiball, --power-ups and --arena");
+			}
+			settings.mode = GameMode::Versus;
+			settings.multiball = false;
+			settings.power_ups = false;
+			settings.arena = None;
+			settings.controllers = [Some(Controller::Remote), Some(Controller::Remote), None, None];
+		}
+		// The server assigns paddles itself
+		if !network.peer.is_empty() && settings.server.is_none() && !settings.is_browsing {
+			settings.controllers[network.side as usize] = Some(Controller::Human(ControlScheme::Any));
+			settings.network = Some(network);
+		}
+
+		settings
+	}
+
+	pub fn is_networked(&self) -> bool {
+		self.network.is_some() || self.server.is_some() || self.is_browsing
+	}
+}
+
+// Resources every plugin reads, inserted by whichever plugin is added first.
+// Settings inserted before the plugins are kept, otherwise the default match is played
+fn init_shared_resources(app: &mut App) {
+	app.init_resource::<Settings>()
+		.init_resource::<Tuning>()
+		.init_resource::<ButtonInput<KeyCode>>();
+
+	let settings = app.world.resource::<Settings>().clone();
+	if !app.world.contains_resource::<Arena>() {
+		app.insert_resource(Arena { goal_size: settings.goal_size, ..default() });
+	}
+	if !app.world.contains_resource::<Scoreboard>() {
+		app.insert_resource(Scoreboard::new(settings.controllers.map(|controller| controller.is_some())));
+	}
+}
+
+// Gameplay without window or rendering: simulated key presses, frames of a fixed length
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::time::TimeUpdateStrategy;
+	use crate::game::ai::{Ai, AiPlan, AiPlugin};
+	use crate::game::input::{AcceptEvent, InputPlugin, Player};
+	use crate::game::physics::PhysicsPlugin;
+	use crate::game::state::{GameplayState, StatePlugin};
+	use crate::game::ui::{ScoreboardUi, UiPlugin};
+	use crate::BALL_SIZE;
+
+	pub(super) const FRAME: Duration    = Duration::from_nanos(16_666_667);
+	pub(super) const MAX_FRAMES: usize  = 600;
+
+	pub(super) fn test_app() -> App {
+		let mut app = App::new();
+		app.add_plugins(MinimalPlugins)
+			.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+			.add_plugins((StatePlugin, InputPlugin, AiPlugin, PhysicsPlugin, UiPlugin))
+			.add_systems(Startup, test_setup);
+		app.update();
+		app
+	}
+
+	// Entities of world_setup the gameplay systems rely on, without meshes
+	fn test_setup(
+		mut commands: Commands,
+		arena: Res<Arena>,
+		tuning: Res<Tuning>,
+	) {
+		commands.spawn((
+			BallBundle::new(tuning.ball_starting_speed),
+			Transform::from_translation(BALL_STARTING_POSITION),
+			Visibility::Hidden,
+		));
+		commands.spawn((
+			PaddleBundle::new(Side::Left, tuning.paddle_size(Side::Left), tuning.player_max_speed, tuning.paddle_motion()),
+			Transform::from_translation(Side::Left.paddle_position(&arena).extend(ZLAYER::MAIN)),
+			Visibility::Inherited,
+			Ai,
+			AiPlan::default(),
+		));
+		commands.spawn((
+			PaddleBundle::new(Side::Right, tuning.paddle_size(Side::Right), tuning.player_max_speed, tuning.paddle_motion()),
+			Transform::from_translation(Side::Right.paddle_position(&arena).extend(ZLAYER::MAIN)),
+			Visibility::Inherited,
+			Player(ControlScheme::Any),
+		));
+	}
+
+	pub(super) fn state(app: &App) -> GameplayState {
+		app.world.resource::<State<GameplayState>>().get().clone()
+	}
+
+	// Steps frames until the state is reached, false if it never is
+	pub(super) fn run_until(app: &mut App, state: GameplayState) -> bool {
+		for _ in 0..MAX_FRAMES {
+			if self::state(app) == state { return true }
+			app.update();
+		}
+		self::state(app) == state
+	}
+
+	pub(super) fn press(app: &mut App, key: KeyCode) {
+		app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+		app.update();
+		let mut keyboard_input = app.world.resource_mut::<ButtonInput<KeyCode>>();
+		keyboard_input.release(key);
+		keyboard_input.clear();
+	}
+
+	pub(super) fn press_accept(app: &mut App) {
+		press(app, KeyCode::Enter);
+	}
+
+	pub(super) fn ball(app: &mut App) -> (Vec3, Vec2, f32, Visibility) {
+		let mut query = app.world.query_filtered::<(&Transform, &Velocity, &MaxSpeed, &Visibility), With<Ball>>();
+		let (transform, velocity, max_speed, visibility) = query.single(&app.world);
+		(transform.translation, velocity.0, max_speed.0, *visibility)
+	}
+
+	pub(super) fn conceded(app: &App) -> [u32; 4] {
+		app.world.resource::<Scoreboard>().conceded
+	}
+
+	pub(super) fn scoreboard_text(app: &mut App) -> String {
+		let mut query = app.world.query_filtered::<&Text, With<ScoreboardUi>>();
+		query.single(&app.world).sections[0].value.clone()
+	}
+
+	// Sends the ball into the goal of the side, away from its paddle
+	pub(super) fn score_against(app: &mut App, side: Side) {
+		let arena = app.world.resource::<Arena>().clone();
+		let x = match side {
+			Side::Left => arena.left_wall() + BALL_SIZE.x / 2.0 - 1.0,
+			_          => arena.right_wall() - BALL_SIZE.x / 2.0 + 1.0,
+		};
+		let mut query = app.world.query_filtered::<(&mut Transform, &mut Velocity), With<Ball>>();
+		let (mut transform, mut velocity) = query.single_mut(&mut app.world);
+		transform.translation = Vec3::new(x, arena.top_wall() / 2.0, ZLAYER::BALL);
+		velocity.0 = side.normal() * BALL_STARTING_SPEED;
+		app.update();
+	}
+
+	pub(super) fn start_match(app: &mut App) {
+		assert!(run_until(app, GameplayState::Instructions));
+		press_accept(app);
+		assert!(run_until(app, GameplayState::Active));
+	}
+
+	#[test]
+	fn tuning_file_matches_built_in_values() {
+		let file = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tuning.ron")).unwrap();
+		let tuning: Tuning = ron::de::from_str(&file).unwrap();
+		assert_eq!(format!("{tuning:?}"), format!("{:?}", Tuning::default()));
+	}
+
+	#[test]
+	fn broken_tunings_are_rejected() {
+		assert_eq!(Tuning::default().validate(), Ok(()));
+		let broken = [
+			Tuning { start_delay: -1.0, ..default() },
+			Tuning { next_set_delay: f32::NAN, ..default() },
+			Tuning { player_max_speed: 0.0, ..default() },
+			Tuning { paddle_size: (10.0, -90.0), ..default() },
+			Tuning { bloom: BloomTuning { intensity: f32::INFINITY, ..Tuning::default().bloom }, ..default() },
+		];
+		for tuning in broken {
+			assert!(tuning.validate().is_err(), "accepted {tuning:?}");
+		}
+	}
+
+	#[test]
+	fn states_and_physics_play_without_other_plugins() {
+		let mut app = App::new();
+		app.add_plugins(MinimalPlugins)
+			.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+			.add_plugins((StatePlugin, PhysicsPlugin))
+			.add_systems(Startup, test_setup);
+		app.update();
+		assert_eq!(state(&app), GameplayState::Instructions);
+
+		app.world.send_event(AcceptEvent);
+		assert!(run_until(&mut app, GameplayState::Active));
+		score_against(&mut app, Side::Left);
+		assert!(run_until(&mut app, GameplayState::NextSet));
+		assert_eq!(conceded(&app), [1, 0, 0, 0]);
+	}
+}
@@ -0,0 +1,190 @@
+// Networked play: peer to peer sessions, dedicated servers and the lobby of the local network.
+// The remote state is mirrored into the entities instead of simulating it locally.
+
+// import bevy
+use bevy::prelude::*;
+
+// import crate
+use crate::game::{init_shared_resources, Ball, NetworkView, Paddle, Scoreboard, DEFAULT_INPUT_DELAY};
+use crate::game::audio::{play_collision_sound, CollisionSound, GlobalVolume};
+use crate::game::input::{read_paddle_input, ControlScheme, Player, KEYCODES_ACCEPT, KEYCODES_PADDLE_LEFT, KEYCODES_PADDLE_RIGHT};
+use crate::game::state::GameplayState;
+use crate::game::ui::{GameOverUi, LobbyUi};
+use crate::net::{NetSession, SessionEnd};
+use crate::protocol::{LobbyBrowser, ServerConnection, Snapshot};
+use crate::simulation::{Phase, SIDES, TICK_RATE};
+use crate::{Arena, Side};
+
+// Peer to peer sessions joined from the lobby listen on any free port
+const LOBBY_BIND_ADDRESS: &str = "0.0.0.0:0";
+
+pub struct NetworkPlugin;
+impl Plugin for NetworkPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Systems: network, the remote state replaces local physics and state switching
+		app.add_systems(FixedUpdate, net_update.run_if(resource_exists::<NetSession>))
+			.add_systems(Update, (
+				server_update.run_if(resource_exists::<ServerConnection>),
+				sync_network_view,
+				show_session_end.run_if(resource_exists::<NetSession>).run_if(in_state(GameplayState::GameOver)),
+				)
+				.chain()
+				.run_if(resource_exists::<NetworkView>)
+				.run_if(not(in_state(GameplayState::Startup))));
+
+		// Systems: lobby, connects by inserting a ServerConnection and a NetworkView
+		app.add_systems(Update, browse_lobby.run_if(in_state(GameplayState::Lobby)));
+	}
+}
+
+fn net_update(
+	mut commands: Commands,
+	mut session: ResMut<NetSession>,
+	mut view: ResMut<NetworkView>,
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	player_query: Query<&Player>,
+	sound: Option<Res<CollisionSound>>,
+	volume: Option<Res<GlobalVolume>>,
+) {
+	let events = session.update(read_paddle_input(&keyboard_input, &player_query));
+
+	let simulation = session.simulation();
+	let players = if session.is_connected() || session.end().is_some() { 2 } else { 1 };
+	view.0 = Snapshot::new(simulation.tick, simulation, players, Some(session.local_side()), false);
+
+	// an ended session stops the match where it was
+	if session.end().is_some() { view.phase = Phase::GameOver }
+
+	if let (true, Some(sound), Some(volume)) = (events.is_some_and(|events| events.collision), sound, volume) {
+		play_collision_sound(&mut commands, &sound, &volume);
+	}
+}
+
+fn server_update(
+	mut commands: Commands,
+	mut connection: ResMut<ServerConnection>,
+	mut view: ResMut<NetworkView>,
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	player_query: Query<&Player>,
+	sound: Option<Res<CollisionSound>>,
+	volume: Option<Res<GlobalVolume>>,
+	time: Res<Time<Real>>,
+) {
+	let now = time.elapsed_seconds();
+	let is_collision = connection.update(read_paddle_input(&keyboard_input, &player_query), now);
+
+	view.0 = connection.interpolated(now)
+		.filter(|_| connection.is_connected(now))
+		.unwrap_or_default();
+
+	if let (true, Some(sound), Some(volume)) = (is_collision, sound, volume) {
+		play_collision_sound(&mut commands, &sound, &volume);
+	}
+}
+
+// Mirror the remote state into entities and GameplayState
+fn sync_network_view(
+	mut commands: Commands,
+	view: Res<NetworkView>,
+	arena: Res<Arena>,
+	current_game_state: Res<State<GameplayState>>,
+	mut next_game_state: ResMut<NextState<GameplayState>>,
+	mut scoreboard: ResMut<Scoreboard>,
+	mut ball_query: Query<&mut Transform, (With<Ball>, Without<Paddle>)>,
+	mut paddle_query: Query<(Entity, &mut Transform, &Side, Has<Player>), With<Paddle>>,
+) {
+	for (index, side) in SIDES.iter().enumerate() {
+		scoreboard.conceded[*side as usize] = view.conceded[index];
+	}
+	for mut transform in &mut ball_query {
+		transform.translation = view.ball_position.extend(transform.translation.z);
+	}
+	for (entity, mut transform, side, is_player) in &mut paddle_query {
+		let Some(index) = SIDES.iter().position(|paddle_side| paddle_side == side) else { continue };
+		let position = side.paddle_position(&arena) + side.axis() * view.paddle_positions[index];
+		transform.translation = position.extend(transform.translation.z);
+
+		// Paddle of this player, for controls and the game over screen
+		let is_local = view.side == Some(*side);
+		if is_local && !is_player { commands.entity(entity).insert(Player(ControlScheme::Any)); }
+		if !is_local && is_player { commands.entity(entity).remove::<Player>(); }
+	}
+
+	let state = match (view.players >= 2, view.phase) {
+		(false, _)                => GameplayState::Instructions,
+		(true, Phase::Start(_))   => GameplayState::Start,
+		(true, Phase::Active)     => GameplayState::Active,
+		(true, Phase::NextSet(_)) => GameplayState::NextSet,
+		(true, Phase::GameOver)   => GameplayState::GameOver,
+	};
+	if *current_game_state.get() == state { return }
+
+	next_game_state.set(state);
+}
+
+// The game over title tells why a peer to peer match stopped early
+fn show_session_end(
+	session: Res<NetSession>,
+	mut query: Query<&mut Text, With<GameOverUi>>,
+) {
+	let value = match session.end() {
+		Some(SessionEnd::Desync(_)) => "OUT OF SYNC",
+		Some(SessionEnd::PeerLeft)  => "PEER LEFT",
+		None                        => return,
+	};
+	for mut text in &mut query {
+		let Some(section) = text.sections.first_mut() else { continue };
+		if section.value != value { section.value = value.into() }
+	}
+}
+
+fn browse_lobby(
+	mut commands: Commands,
+	mut browser: ResMut<LobbyBrowser>,
+	arena: Res<Arena>,
+	keyboard_input: Res<ButtonInput<KeyCode>>,
+	mut query: Query<&mut Text, With<LobbyUi>>,
+	time: Res<Time<Real>>,
+) {
+	browser.update(time.elapsed_seconds());
+
+	let last = browser.entries.len().saturating_sub(1);
+	if keyboard_input.any_just_pressed(KEYCODES_PADDLE_RIGHT) { browser.selected = browser.selected.saturating_sub(1) }
+	if keyboard_input.any_just_pressed(KEYCODES_PADDLE_LEFT)  { browser.selected = (browser.selected + 1).min(last) }
+
+	if keyboard_input.any_just_pressed(KEYCODES_ACCEPT) {
+		if let Some(entry) = browser.selected() {
+			let address = entry.address.to_string();
+			let joined = match entry.advert.peer_side {
+				Some(side) => NetSession::new(LOBBY_BIND_ADDRESS, &address, side as usize, DEFAULT_INPUT_DELAY, arena.clone())
+					.map(|session| commands.insert_resource(session)),
+				None => ServerConnection::new(&address, false)
+					.map(|connection| commands.insert_resource(connection)),
+			};
+			match joined {
+				Ok(()) => {
+					commands.insert_resource(NetworkView::default());
+					commands.insert_resource(Time::<Fixed>::from_hz(TICK_RATE));
+				}
+				Err(error) => error!("LOBBY: {error}"),
+			}
+		}
+	}
+
+	let Ok(mut text) = query.get_single_mut() else { return };
+	let Some(section) = text.sections.first_mut() else { return };
+	section.value = match browser.entries.is_empty() {
+		true  => "Searching the local network...".into(),
+		false => browser.entries.iter().enumerate()
+			.map(|(index, entry)| format!("{} {}  {}/2  {}",
+				if index == browser.selected { ">" } else { " " },
+				entry.advert.name,
+				entry.advert.players,
+				entry.advert.rules,
+			))
+			.collect::<Vec<_>>()
+			.join("\n"),
+	};
+}
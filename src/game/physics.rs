@@ -0,0 +1,664 @@
+// Movement of balls and paddles, collisions with walls, paddles and obstacles, goals,
+// extra balls of multiball and power-ups.
+// Reports collisions and goals as events; what they lead to is up to the other plugins.
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::math::bounding::{Aabb2d, BoundingVolume, IntersectsVolume};
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+// import custom
+use rand::Rng;
+
+// import crate
+use crate::game::{
+	init_shared_resources, Ball, BallBundle, Collider, Eliminated, ExtraBall, Intent, LastTouch, MaxSpeed, NetworkView, Paddle,
+	Scoreboard, Settings, Tuning, Velocity, ZLAYER, BACKGROUND_COLOR, BALL_STARTING_POSITION,
+};
+use crate::game::ai::AiSet;
+use crate::game::arena::Obstacle;
+use crate::game::input::InputSet;
+use crate::game::state::GameplayState;
+use crate::game::ui::{GLOBAL_TEXT_SCALE, POWER_UP_FONT_SIZE};
+use crate::{bounce_ball, speed_up, Arena, BallCollider, PaddleMotion, Side, BALL_SIZE, SIN_OF_45};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum PowerUpKind { Enlarge, Shrink, SpeedBoost, SlowBall, Sticky, Shield }
+
+// Paddles move first, then balls bounce off them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub enum PhysicsSet { Movement, Collision }
+
+const MULTIBALL_SPAWN_INTERVAL: Duration = Duration::from_secs(8);
+const MULTIBALL_HITS_TO_SPAWN: u32       = 4;
+const MULTIBALL_MAX_BALLS: usize         = 4;
+
+pub const POWER_UP_SIZE: Vec2           = Vec2::new(16.0, 16.0);
+const POWER_UP_SPAWN_INTERVAL: Duration = Duration::from_secs(6);
+const POWER_UP_DURATION: Duration       = Duration::from_secs(8);
+const POWER_UP_MAX_PICKUPS: usize       = 2;
+const POWER_UP_SPAWN_ATTEMPTS: usize    = 8;
+const ENLARGE_SCALE: f32                = 1.5;
+const SHRINK_SCALE: f32                 = 0.6;
+const SPEED_BOOST_SCALE: f32            = 1.5;
+const SLOW_BALL_SCALE: f32              = 0.6;
+const STICKY_HOLD: Duration             = Duration::from_millis(600);
+
+// Components
+#[derive(Component, Deref, DerefMut)] pub struct PowerUp(pub PowerUpKind);
+#[derive(Component, Default, Deref, DerefMut)] pub struct PowerUps(pub Vec<(PowerUpKind, Timer)>);
+#[derive(Component)] pub struct Stuck { paddle: Entity, offset: Vec2, timer: Timer }
+
+// Events
+#[derive(Event)] pub struct CollisionEvent { pub ball: Entity, pub with_collider: bool }
+#[derive(Event)] pub struct GoalEvent { pub side: Side, pub ends_set: bool } // extra balls score without ending the set
+
+// Resources
+#[derive(Resource)] pub struct BallAssets { pub mesh: Mesh2dHandle, pub material: Handle<ColorMaterial> }
+#[derive(Resource)] pub struct Multiball { timer: Timer, hits: u32 }
+#[derive(Resource)] pub struct PowerUpAssets { pub mesh: Mesh2dHandle, pub material: Handle<ColorMaterial>, pub font: Handle<Font> }
+#[derive(Resource, Deref, DerefMut)] pub struct PowerUpTimer(Timer);
+#[derive(Resource, Deref, DerefMut)] pub struct BallSpeedScale(pub f32);
+
+pub struct PhysicsPlugin;
+impl Plugin for PhysicsPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Events
+		app.add_event::<CollisionEvent>()
+			.add_event::<GoalEvent>();
+
+		// Resources
+		app.insert_resource(Multiball {
+				timer: Timer::new(MULTIBALL_SPAWN_INTERVAL, TimerMode::Repeating),
+				hits: 0,
+			})
+			.insert_resource(PowerUpTimer(Timer::new(POWER_UP_SPAWN_INTERVAL, TimerMode::Repeating)))
+			.insert_resource(BallSpeedScale(1.0));
+
+		// Transitions
+		app.add_systems(OnEnter(GameplayState::Active), start_game_set)
+			.add_systems(OnExit(GameplayState::Active), (reset_game_set, despawn_power_ups))
+			.add_systems(OnEnter(GameplayState::GameOver), clear_power_ups);
+
+		// Systems: update, the remote state replaces local physics in networked play
+		app.configure_sets(Update, (PhysicsSet::Movement, PhysicsSet::Collision)
+				.chain()
+				.after(InputSet)
+				.after(AiSet)
+				.run_if(not(in_state(GameplayState::Startup)))
+				.run_if(not(resource_exists::<NetworkView>)))
+			.add_systems(Update,
+				(
+				move_paddles,
+				limit_velocity,
+				apply_velocity,
+				bound_paddle,
+				)
+				.chain()
+				.in_set(PhysicsSet::Movement)
+				)
+			.add_systems(Update,
+				(
+				carry_stuck_balls,
+				check_ball_collisions,
+				speed_up_balls,
+				spawn_extra_balls.run_if(multiball_enabled),
+					(
+					spawn_power_ups,
+					collect_power_ups,
+					tick_power_ups,
+					)
+					.chain()
+					.run_if(power_ups_enabled),
+				)
+				.chain()
+				.in_set(PhysicsSet::Collision)
+				.run_if(in_state(GameplayState::Active))
+				);
+	}
+}
+
+impl PowerUpKind {
+	const ALL: [PowerUpKind; 6] = [
+		PowerUpKind::Enlarge,
+		PowerUpKind::Shrink,
+		PowerUpKind::SpeedBoost,
+		PowerUpKind::SlowBall,
+		PowerUpKind::Sticky,
+		PowerUpKind::Shield,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			PowerUpKind::Enlarge    => "ENLARGE",
+			PowerUpKind::Shrink     => "SHRINK",
+			PowerUpKind::SpeedBoost => "SPEED",
+			PowerUpKind::SlowBall   => "SLOW BALL",
+			PowerUpKind::Sticky     => "STICKY",
+			PowerUpKind::Shield     => "SHIELD",
+		}
+	}
+
+	// Drawn on the pickup
+	fn label(self) -> &'static str {
+		match self {
+			PowerUpKind::Enlarge    => "+",
+			PowerUpKind::Shrink     => "-",
+			PowerUpKind::SpeedBoost => ">",
+			PowerUpKind::SlowBall   => "<",
+			PowerUpKind::Sticky     => "*",
+			PowerUpKind::Shield     => "#",
+		}
+	}
+
+	// Multipliers of paddle length and paddle speed while active
+	fn paddle_factors(self) -> (f32, f32) {
+		match self {
+			PowerUpKind::Enlarge    => (ENLARGE_SCALE, 1.0),
+			PowerUpKind::Shrink     => (SHRINK_SCALE, 1.0),
+			PowerUpKind::SpeedBoost => (1.0, SPEED_BOOST_SCALE),
+			_                       => (1.0, 1.0),
+		}
+	}
+}
+
+impl PowerUps {
+	pub fn has(&self, kind: PowerUpKind) -> bool {
+		self.iter().any(|(active, _)| *active == kind)
+	}
+
+	// Remove the power-up, returns whether it was active
+	fn take(&mut self, kind: PowerUpKind) -> bool {
+		let count = self.len();
+		self.retain(|(active, _)| *active != kind);
+		self.len() != count
+	}
+}
+
+// Controllers only ask, every paddle gets there within the limits of its motion
+fn move_paddles(
+	mut query: Query<(&mut Velocity, &Intent, &PaddleMotion, &MaxSpeed, &Side), With<Paddle>>,
+	time: Res<Time>,
+) {
+	for (mut velocity, intent, motion, max_speed, side) in &mut query {
+		let speed = motion.step(velocity.dot(side.axis()), intent.0, max_speed.0, time.delta_seconds());
+		velocity.0 = side.axis() * speed;
+	}
+}
+
+fn limit_velocity(
+	mut query: Query<(&mut Velocity, &MaxSpeed)>,
+) {
+	for (mut velocity, max_speed) in &mut query
+	{
+		velocity.0 = velocity.clamp_length_max(max_speed.0);
+	}
+}
+
+fn apply_velocity(
+	mut query: Query<(&mut Transform, &Velocity, Has<Ball>), Without<Stuck>>,
+	ball_speed_scale: Res<BallSpeedScale>,
+	time: Res<Time>
+) {
+	for (mut transform, velocity, is_ball) in &mut query {
+		let scale = if is_ball { ball_speed_scale.0 } else { 1.0 };
+		transform.translation.x += velocity.x * scale * time.delta_seconds();
+		transform.translation.y += velocity.y * scale * time.delta_seconds();
+	}
+}
+
+fn bound_paddle(
+	settings: Res<Settings>,
+	arena: Res<Arena>,
+	tuning: Res<Tuning>,
+	mut query: Query<(&mut Transform, &mut Velocity, &Side), With<Paddle>>,
+) {
+	let size = tuning.paddle_size(Side::Left);
+	for (mut transform, mut velocity, side) in &mut query
+	{
+		let axis = side.axis();
+		let extra_length = size.y * (transform.scale.xy().dot(axis) - 1.0);
+		let bound = settings.mode.sized_paddle_bound(*side, &arena, size) - extra_length / 2.0;
+		let translation = transform.translation.xy().dot(axis);
+		let translation_goal = translation.clamp(-bound, bound);
+
+		if translation == translation_goal { continue }
+
+		transform.translation += (axis * (translation_goal - translation)).extend(0.0);
+		let speed = velocity.dot(axis);
+		velocity.0 -= axis * speed;
+	}
+}
+
+fn check_ball_collisions(
+	mut commands: Commands,
+	mut scoreboard: ResMut<Scoreboard>,
+	arena: Res<Arena>,
+	mut ball_query: Query<(Entity, &mut Velocity, &mut Transform, &mut LastTouch, Has<ExtraBall>), (With<Ball>, Without<Stuck>)>,
+	mut collider_query: Query<(Entity, &Transform, &Collider, Option<&Side>, Option<&mut PowerUps>), (Without<Eliminated>, Without<Ball>)>,
+	mut collision_events: EventWriter<CollisionEvent>,
+	mut goal_events: EventWriter<GoalEvent>,
+) {
+	let (entities, colliders): (Vec<_>, Vec<_>) = collider_query.iter()
+		.map(|(entity, transform, collider, paddle_side, power_ups)| {
+			let is_sticky = power_ups.is_some_and(|power_ups| power_ups.has(PowerUpKind::Sticky));
+			let aabb = Aabb2d::new(transform.translation.xy(), collider.0 * transform.scale.xy() / 2.0);
+			((entity, is_sticky), BallCollider { aabb, paddle: paddle_side.copied() })
+		})
+		.unzip();
+
+	for (ball, mut ball_velocity, mut ball_transform, mut last_touch, is_extra) in &mut ball_query
+	{
+		let bounce = bounce_ball(ball_transform.translation.xy(), ball_velocity.0, &colliders, &arena);
+
+		// process scoreboard: walls of defending sides are goals
+		let mut goal = scoreboard.goal(bounce.walls, ball_transform.translation.xy(), &arena);
+
+		// shield is used up by the goal it saves
+		if let Some(side) = goal {
+			let power_ups = collider_query.iter_mut()
+				.find(|(_, _, _, paddle_side, _)| *paddle_side == Some(&side))
+				.and_then(|(_, _, _, _, power_ups)| power_ups);
+			if let Some(mut power_ups) = power_ups {
+				if power_ups.take(PowerUpKind::Shield) { goal = None }
+			}
+		}
+
+		if let Some(side) = goal {
+			scoreboard.concede(side);
+
+			// extra balls leave the arena while nobody is eliminated
+			if is_extra && scoreboard.is_defending(side) {
+				goal_events.send(GoalEvent { side, ends_set: false });
+				commands.entity(ball).despawn();
+				continue;
+			}
+			goal_events.send(GoalEvent { side, ends_set: true });
+		}
+		ball_transform.translation = bounce.position.extend(ball_transform.translation.z);
+		ball_velocity.0 = bounce.velocity;
+
+		for (index, position) in bounce.hits.iter().copied() {
+			let ((entity, is_sticky), collider) = (entities[index], colliders[index]);
+			let Some(paddle_side) = collider.paddle else { continue };
+			last_touch.0 = Some(entity);
+
+			// sticky paddle holds the ball in front of itself
+			if is_sticky {
+				let offset = position - collider.aabb.center();
+				let depth = collider.aabb.half_size().dot(paddle_side.normal().abs()) + BALL_SIZE.x / 2.0 + 1.0;
+				commands.entity(ball).insert(Stuck {
+					paddle: entity,
+					offset: paddle_side.axis() * offset.dot(paddle_side.axis()) - paddle_side.normal() * depth,
+					timer: Timer::new(STICKY_HOLD, TimerMode::Once),
+				});
+			}
+		}
+
+		// collision event
+		if bounce.is_collision() {
+			collision_events.send(CollisionEvent { ball, with_collider: !bounce.hits.is_empty() });
+		}
+	}
+}
+
+fn speed_up_balls(
+	mut collision_events: EventReader<CollisionEvent>,
+	mut query: Query<(&mut Velocity, &mut MaxSpeed), With<Ball>>,
+	tuning: Res<Tuning>,
+) {
+	for event in collision_events.read() {
+		let Ok((mut velocity, mut max_speed)) = query.get_mut(event.ball) else { continue };
+		(velocity.0, max_speed.0) = speed_up(velocity.0, max_speed.0, tuning.ball_delta_speed);
+	}
+}
+
+fn multiball_enabled(settings: Res<Settings>) -> bool {
+	settings.multiball
+}
+
+fn spawn_extra_balls(
+	mut commands: Commands,
+	mut multiball: ResMut<Multiball>,
+	mut collision_events: EventReader<CollisionEvent>,
+	ball_assets: Res<BallAssets>,
+	ball_query: Query<(), With<Ball>>,
+	tuning: Res<Tuning>,
+	time: Res<Time>,
+) {
+	multiball.hits += collision_events.read().filter(|event| event.with_collider).count() as u32;
+	multiball.timer.tick(time.delta());
+
+	let is_due = multiball.timer.just_finished() || multiball.hits >= MULTIBALL_HITS_TO_SPAWN;
+	let ball_count = ball_query.iter().count();
+	if !is_due || ball_count >= MULTIBALL_MAX_BALLS { return }
+
+	multiball.hits = 0;
+	multiball.timer.reset();
+
+	// every next ball leaves the center along another diagonal
+	let angle = std::f32::consts::FRAC_PI_4 + std::f32::consts::FRAC_PI_2 * ball_count as f32;
+	let mut ball = BallBundle::new(tuning.ball_starting_speed);
+	ball.velocity.0 = Vec2::from_angle(angle) * tuning.ball_starting_speed;
+
+	commands.spawn((
+		ball,
+		ExtraBall,
+		MaterialMesh2dBundle {
+			mesh: ball_assets.mesh.clone(),
+			material: ball_assets.material.clone(),
+			transform: Transform::from_translation(BALL_STARTING_POSITION),
+			..default()
+		},
+	));
+}
+
+pub fn power_ups_enabled(settings: Res<Settings>) -> bool {
+	settings.power_ups
+}
+
+fn spawn_power_ups(
+	mut commands: Commands,
+	mut timer: ResMut<PowerUpTimer>,
+	assets: Res<PowerUpAssets>,
+	arena: Res<Arena>,
+	pickup_query: Query<(), With<PowerUp>>,
+	obstacle_query: Query<(&Transform, &Collider), With<Obstacle>>,
+	time: Res<Time>,
+) {
+	timer.tick(time.delta());
+	if !timer.just_finished() || pickup_query.iter().count() >= POWER_UP_MAX_PICKUPS { return }
+
+	let obstacles: Vec<Aabb2d> = obstacle_query.iter()
+		.map(|(transform, collider)| Aabb2d::new(transform.translation.xy(), collider.0 * transform.scale.xy() / 2.0))
+		.collect();
+	let mut rng = rand::thread_rng();
+	let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+	// crowded arenas skip this spawn, the timer tries again
+	let Some(position) = power_up_spawn_point(&mut rng, &arena, &obstacles) else { return };
+
+	commands.spawn((
+		PowerUp(kind),
+		MaterialMesh2dBundle {
+			mesh: assets.mesh.clone(),
+			material: assets.material.clone(),
+			transform: Transform::from_translation(position.extend(ZLAYER::MAIN)),
+			..default()
+		},
+	)).with_children(|parent| {
+		parent.spawn(Text2dBundle {
+			text: Text::from_section(kind.label(), TextStyle {
+				font: assets.font.clone(),
+				font_size: POWER_UP_FONT_SIZE,
+				color: BACKGROUND_COLOR }),
+			transform: Transform::from_xyz(0.0, 0.0, ZLAYER::TEXT).with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+			..default()
+		});
+	});
+}
+
+// Random point in the middle of the arena where a pickup doesn't overlap any obstacle
+fn power_up_spawn_point(rng: &mut impl Rng, arena: &Arena, obstacles: &[Aabb2d]) -> Option<Vec2> {
+	(0..POWER_UP_SPAWN_ATTEMPTS)
+		.map(|_| Vec2::new(rng.gen_range(-0.6..0.6), rng.gen_range(-0.6..0.6)) * arena.paddle_offset())
+		.find(|position| {
+			let pickup = Aabb2d::new(*position, POWER_UP_SIZE / 2.0);
+			!obstacles.iter().any(|obstacle| obstacle.intersects(&pickup))
+		})
+}
+
+fn collect_power_ups(
+	mut commands: Commands,
+	ball_query: Query<(&Transform, &LastTouch), (With<Ball>, Without<Paddle>)>,
+	pickup_query: Query<(Entity, &Transform, &PowerUp), (Without<Ball>, Without<Paddle>)>,
+	mut paddle_query: Query<(Entity, &mut PowerUps, &mut Transform, &mut MaxSpeed, &Side), (With<Paddle>, Without<Eliminated>)>,
+) {
+	for (pickup, pickup_transform, kind) in &pickup_query {
+		let pickup_aabb = Aabb2d::new(pickup_transform.translation.xy(), POWER_UP_SIZE / 2.0);
+
+		// awarded to whoever last touched the ball, untouched balls pass through
+		let winner = ball_query.iter()
+			.filter(|(ball_transform, _)| Aabb2d::new(ball_transform.translation.xy(), BALL_SIZE / 2.0).intersects(&pickup_aabb))
+			.find_map(|(_, last_touch)| last_touch.0);
+		let Some(winner) = winner else { continue };
+
+		commands.entity(pickup).despawn_recursive();
+
+		for (paddle, mut power_ups, mut transform, mut max_speed, side) in &mut paddle_query {
+			// shrink hits everyone else
+			let is_target = match kind.0 {
+				PowerUpKind::Shrink => paddle != winner,
+				_                   => paddle == winner,
+			};
+			if !is_target { continue }
+
+			// picking an active power-up again refreshes its duration
+			if !power_ups.take(kind.0) {
+				apply_paddle_factors(kind.0, *side, &mut transform, &mut max_speed, false);
+			}
+			power_ups.push((kind.0, Timer::new(POWER_UP_DURATION, TimerMode::Once)));
+		}
+	}
+}
+
+fn tick_power_ups(
+	mut paddle_query: Query<(&mut PowerUps, &mut Transform, &mut MaxSpeed, &Side), With<Paddle>>,
+	mut ball_speed_scale: ResMut<BallSpeedScale>,
+	time: Res<Time>,
+) {
+	let mut is_ball_slow = false;
+
+	for (mut power_ups, mut transform, mut max_speed, side) in &mut paddle_query {
+		for (kind, timer) in power_ups.iter_mut() {
+			timer.tick(time.delta());
+			if timer.just_finished() {
+				apply_paddle_factors(*kind, *side, &mut transform, &mut max_speed, true);
+			}
+		}
+		power_ups.retain(|(_, timer)| !timer.finished());
+		is_ball_slow |= power_ups.has(PowerUpKind::SlowBall);
+	}
+
+	ball_speed_scale.0 = if is_ball_slow { SLOW_BALL_SCALE } else { 1.0 };
+}
+
+fn apply_paddle_factors(
+	kind: PowerUpKind,
+	side: Side,
+	transform: &mut Transform,
+	max_speed: &mut MaxSpeed,
+	is_revert: bool,
+) {
+	let (mut length_factor, mut speed_factor) = kind.paddle_factors();
+	if is_revert {
+		length_factor = length_factor.recip();
+		speed_factor = speed_factor.recip();
+	}
+
+	let scale = transform.scale.xy() * (Vec2::ONE + side.axis() * (length_factor - 1.0));
+	transform.scale = scale.extend(transform.scale.z);
+	max_speed.0 *= speed_factor;
+}
+
+fn carry_stuck_balls(
+	mut commands: Commands,
+	mut ball_query: Query<(Entity, &mut Transform, &mut Stuck), With<Ball>>,
+	paddle_query: Query<&Transform, (With<Paddle>, Without<Ball>)>,
+	time: Res<Time>,
+) {
+	for (ball, mut transform, mut stuck) in &mut ball_query {
+		if let Ok(paddle_transform) = paddle_query.get(stuck.paddle) {
+			let position = paddle_transform.translation.xy() + stuck.offset;
+			transform.translation = position.extend(transform.translation.z);
+		}
+
+		stuck.timer.tick(time.delta());
+		if stuck.timer.finished() {
+			commands.entity(ball).remove::<Stuck>();
+		}
+	}
+}
+
+fn despawn_power_ups(
+	mut commands: Commands,
+	pickup_query: Query<Entity, With<PowerUp>>,
+) {
+	for pickup in &pickup_query {
+		commands.entity(pickup).despawn_recursive();
+	}
+}
+
+fn clear_power_ups(
+	mut paddle_query: Query<(&mut PowerUps, &mut Transform, &mut MaxSpeed, &Side), With<Paddle>>,
+	mut ball_speed_scale: ResMut<BallSpeedScale>,
+) {
+	for (mut power_ups, mut transform, mut max_speed, side) in &mut paddle_query {
+		for (kind, _) in power_ups.drain(..) {
+			apply_paddle_factors(kind, *side, &mut transform, &mut max_speed, true);
+		}
+	}
+
+	ball_speed_scale.0 = 1.0;
+}
+
+fn start_game_set(
+	mut ball_query: Query<&mut Velocity, With<Ball>>,
+	mut multiball: ResMut<Multiball>,
+	tuning: Res<Tuning>,
+) {
+	for mut ball_velocity in &mut ball_query {
+		ball_velocity.0 = Vec2::new(SIN_OF_45, SIN_OF_45) * tuning.ball_starting_speed;
+	}
+
+	multiball.timer.reset();
+	multiball.hits = 0;
+}
+
+fn reset_game_set(
+	mut commands: Commands,
+	mut ball_query: Query<(Entity, &mut Velocity, &mut MaxSpeed, &mut Transform, &mut LastTouch, Has<ExtraBall>), With<Ball>>,
+	tuning: Res<Tuning>,
+) {
+	for (entity, mut ball_velocity, mut max_speed, mut ball_transform, mut last_touch, is_extra) in &mut ball_query {
+		if is_extra {
+			commands.entity(entity).despawn();
+			continue;
+		}
+
+		ball_velocity.0 = Vec2::ZERO;
+		max_speed.0 = tuning.ball_starting_speed;
+		ball_transform.translation = BALL_STARTING_POSITION;
+		last_touch.0 = None;
+		commands.entity(entity).remove::<Stuck>();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+	use crate::game::tests::{ball, conceded, run_until, score_against, scoreboard_text, start_match, test_app};
+	use crate::game::state::GameplayState;
+	use crate::{GameMode, BALL_STARTING_SPEED};
+	use proptest::prelude::*;
+
+	const EPSILON: f32 = 1e-3;
+
+	#[test]
+	fn power_ups_spawn_clear_of_obstacles() {
+		let arena = Arena::default();
+		let mut rng = StdRng::seed_from_u64(3);
+		let obstacles = [Aabb2d::new(Vec2::ZERO, arena.paddle_offset() * 0.3)];
+
+		for _ in 0..200 {
+			let Some(position) = power_up_spawn_point(&mut rng, &arena, &obstacles) else { continue };
+			assert!(!obstacles[0].intersects(&Aabb2d::new(position, POWER_UP_SIZE / 2.0)), "spawned at {position}");
+		}
+
+		// nowhere to go, nothing spawns
+		let wall = [Aabb2d::new(Vec2::ZERO, arena.size)];
+		assert_eq!(power_up_spawn_point(&mut rng, &arena, &wall), None);
+	}
+
+	proptest! {
+		#[test]
+		fn bound_paddles_stay_inside_the_arena(
+			size in (400.0f32..1600.0, 300.0f32..1200.0),
+			scales in prop::array::uniform4(SHRINK_SCALE * SHRINK_SCALE..ENLARGE_SCALE * ENLARGE_SCALE),
+			positions in prop::array::uniform4(-1000.0f32..1000.0),
+			is_four_players in any::<bool>(),
+		) {
+			let arena = Arena { size: Vec2::new(size.0, size.1), ..default() };
+			let (mode, sides) = match is_four_players {
+				true  => (GameMode::FourPlayers, &Side::ALL[..]),
+				false => (GameMode::Versus, &[Side::Left, Side::Right][..]),
+			};
+			let mut app = App::new();
+			app.insert_resource(Settings { mode, ..default() })
+				.insert_resource(arena.clone())
+				.init_resource::<Tuning>()
+				.add_systems(Update, bound_paddle);
+
+			// power-ups scale paddles along their side
+			for side in sides {
+				let translation = side.paddle_position(&arena) + side.axis() * positions[*side as usize];
+				let scale = Vec2::ONE + side.axis() * (scales[*side as usize] - 1.0);
+				app.world.spawn((
+					Paddle,
+					*side,
+					Transform::from_translation(translation.extend(0.0)).with_scale(scale.extend(1.0)),
+					Velocity(side.axis() * 100.0),
+				));
+			}
+			app.update();
+
+			// paddles as they collide, within the walls and clear of each other
+			let tuning = Tuning::default();
+			let paddles: Vec<(Side, Aabb2d, Vec2, Vec2)> = app.world.query::<(&Side, &Transform, &Velocity)>()
+				.iter(&app.world)
+				.map(|(side, transform, velocity)| {
+					let translation = transform.translation.xy();
+					(*side, Aabb2d::new(translation, tuning.paddle_size(*side) * transform.scale.xy() / 2.0), translation, velocity.0)
+				})
+				.collect();
+			for (side, paddle, translation, velocity) in &paddles {
+				prop_assert!(paddle.min.x >= arena.left_wall() - EPSILON && paddle.max.x <= arena.right_wall() + EPSILON, "{side:?} paddle at {translation}");
+				prop_assert!(paddle.min.y >= arena.bottom_wall() - EPSILON && paddle.max.y <= arena.top_wall() + EPSILON, "{side:?} paddle at {translation}");
+				// paddles held back stop along their side
+				if *translation != side.paddle_position(&arena) + side.axis() * positions[*side as usize] {
+					prop_assert_eq!(velocity.dot(side.axis()), 0.0);
+				}
+				for (other, other_paddle, ..) in &paddles {
+					let overlap = paddle.max.min(other_paddle.max) - paddle.min.max(other_paddle.min);
+					prop_assert!(side == other || overlap.x <= EPSILON || overlap.y <= EPSILON, "{side:?} paddle overlaps the {other:?} one");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn goal_scores_and_resets_ball() {
+		let mut app = test_app();
+		start_match(&mut app);
+
+		score_against(&mut app, Side::Left);
+		assert!(run_until(&mut app, GameplayState::NextSet));
+		assert_eq!(conceded(&app), [1, 0, 0, 0]);
+		assert_eq!(scoreboard_text(&mut app), "0 1");
+
+		let (position, velocity, max_speed, _) = ball(&mut app);
+		assert_eq!(position, BALL_STARTING_POSITION);
+		assert_eq!(velocity, Vec2::ZERO);
+		assert_eq!(max_speed, BALL_STARTING_SPEED);
+
+		assert!(run_until(&mut app, GameplayState::Active));
+		score_against(&mut app, Side::Right);
+		assert!(run_until(&mut app, GameplayState::NextSet));
+		assert_eq!(conceded(&app), [1, 1, 0, 0]);
+		assert_eq!(scoreboard_text(&mut app), "1 1");
+	}
+}
@@ -0,0 +1,493 @@
+// States of a match and the switches between them: instructions, the countdown before a match,
+// sets, the pause between sets and the game over screen.
+// Switches follow the table of a StateMachine: triggers, guards, timers and actions on entering a state.
+// Other plugins hook their own systems into OnEnter/OnExit of these states or read TransitionEvents.
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+
+// import crate
+use crate::game::{init_shared_resources, Ball, Eliminated, NetworkView, Paddle, Scoreboard, Settings, Tuning};
+use crate::game::input::{AcceptEvent, InputSet};
+use crate::game::physics::{GoalEvent, PhysicsSet};
+use crate::Side;
+
+#[derive(States, Debug, Clone, PartialEq, Eq, Hash)] pub enum GameplayState {
+	Startup,
+	Lobby,
+	Instructions,
+	Start,
+	Active,
+	NextSet,
+	GameOver,
+}
+
+// What asks for a switch, sent as an event by any plugin
+#[derive(Event, Debug, PartialEq, Eq, Copy, Clone)] pub enum Trigger {
+	Startup,
+	Accept,
+	Timeout, // the timer started on entering the state finished
+	SetOver,
+	Pause,
+	Resume,
+}
+
+// Facts a transition may require
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum Guard {
+	Always,
+	Browsing,
+	Decided,   // at most one side left defending
+	Undecided,
+}
+
+// Taken with every transition into the state
+#[derive(Debug, PartialEq, Copy, Clone)] pub enum Action {
+	StartTimer(Delay),
+	ResetScoreboard,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)] pub enum Delay {
+	Start,   // from the tuning
+	NextSet, // from the tuning
+	Seconds(f32),
+}
+
+// Timers, answers to prompts and goals that switch the state
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub struct StateSet;
+
+#[derive(Debug, Clone)] pub struct Transition<S> {
+	pub from: S,
+	pub trigger: Trigger,
+	pub guard: Guard,
+	pub to: S,
+}
+
+#[derive(Debug, Clone, Copy, Default)] pub struct Conditions {
+	pub is_browsing: bool,
+	pub is_decided: bool,
+}
+
+// Events
+#[derive(Event, Debug, Clone)] pub struct TransitionEvent<S: States> { pub from: S, pub to: S, pub trigger: Trigger }
+
+// Resources
+#[derive(Resource, Debug, Clone)] pub struct StateMachine<S: States> {
+	pub transitions: Vec<Transition<S>>, // the first allowed one is taken
+	pub on_enter: Vec<(S, Action)>,
+}
+#[derive(Resource, Deref, DerefMut)] pub struct StateTimer(pub Timer);
+
+pub struct StatePlugin;
+impl Plugin for StatePlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// States
+		app.insert_state(GameplayState::Startup)
+			.init_resource::<StateMachine<GameplayState>>();
+
+		// Resources, the timer runs once started by the machine
+		let mut timer = Timer::default();
+		timer.pause();
+		app.insert_resource(StateTimer(timer));
+
+		// Events
+		app.add_event::<Trigger>()
+			.add_event::<TransitionEvent<GameplayState>>()
+			.add_event::<AcceptEvent>()
+			.add_event::<GoalEvent>();
+
+		// Transitions
+		app.add_systems(OnExit(GameplayState::Instructions), unhide_ball)
+			.add_systems(OnExit(GameplayState::Active), eliminate_paddles)
+			.add_systems(OnEnter(GameplayState::GameOver), hide_ball)
+			.add_systems(OnExit(GameplayState::GameOver), (restore_paddles, unhide_ball));
+
+		// Systems: startup
+		app.add_systems(Startup, (start_game, run_state_machine).chain());
+
+		// Systems: update, the networked game follows the state of the peers
+		app.configure_sets(Update, StateSet
+				.after(InputSet)
+				.after(PhysicsSet::Collision)
+				.run_if(not(resource_exists::<NetworkView>)))
+			.add_systems(Update,
+				(
+				(tick_timer, accept_to_trigger, goals_to_trigger),
+				run_state_machine,
+				)
+				.chain()
+				.in_set(StateSet)
+				);
+	}
+}
+
+impl Guard {
+	pub fn allows(self, conditions: &Conditions) -> bool {
+		match self {
+			Guard::Always    => true,
+			Guard::Browsing  => conditions.is_browsing,
+			Guard::Decided   => conditions.is_decided,
+			Guard::Undecided => !conditions.is_decided,
+		}
+	}
+}
+
+impl Delay {
+	pub fn duration(self, tuning: &Tuning) -> Duration {
+		let seconds = match self {
+			Delay::Start          => tuning.start_delay,
+			Delay::NextSet        => tuning.next_set_delay,
+			Delay::Seconds(value) => value,
+		};
+		Duration::from_secs_f32(seconds)
+	}
+}
+
+impl<S: States> StateMachine<S> {
+	pub fn new() -> Self {
+		Self { transitions: Vec::new(), on_enter: Vec::new() }
+	}
+
+	pub fn with_transition(mut self, from: S, trigger: Trigger, guard: Guard, to: S) -> Self {
+		self.transitions.push(Transition { from, trigger, guard, to });
+		self
+	}
+
+	pub fn with_action(mut self, state: S, action: Action) -> Self {
+		self.on_enter.push((state, action));
+		self
+	}
+
+	// State the trigger switches to, if any transition allows it
+	pub fn next(&self, from: &S, trigger: Trigger, conditions: &Conditions) -> Option<&S> {
+		self.transitions.iter()
+			.find(|transition| transition.from == *from && transition.trigger == trigger && transition.guard.allows(conditions))
+			.map(|transition| &transition.to)
+	}
+
+	pub fn actions<'a>(&'a self, state: &'a S) -> impl Iterator<Item = Action> + 'a {
+		self.on_enter.iter()
+			.filter(move |(entered, _)| entered == state)
+			.map(|(_, action)| *action)
+	}
+}
+
+// A match: instructions or the lobby, then countdown, sets and pauses until one side is left
+impl Default for StateMachine<GameplayState> {
+	fn default() -> Self {
+		use GameplayState::*;
+
+		Self::new()
+			.with_transition(Startup,      Trigger::Startup, Guard::Browsing,  Lobby)
+			.with_transition(Startup,      Trigger::Startup, Guard::Always,    Instructions)
+			.with_transition(Instructions, Trigger::Accept,  Guard::Always,    Start)
+			.with_transition(Start,        Trigger::Timeout, Guard::Always,    Active)
+			.with_transition(Active,       Trigger::SetOver, Guard::Decided,   GameOver)
+			.with_transition(Active,       Trigger::SetOver, Guard::Undecided, NextSet)
+			.with_transition(NextSet,      Trigger::Timeout, Guard::Always,    Active)
+			.with_transition(GameOver,     Trigger::Accept,  Guard::Always,    Start)
+			.with_action(Start,   Action::ResetScoreboard)
+			.with_action(Start,   Action::StartTimer(Delay::Start))
+			.with_action(NextSet, Action::StartTimer(Delay::NextSet))
+	}
+}
+
+fn start_game(
+	mut triggers: EventWriter<Trigger>,
+) {
+	triggers.send(Trigger::Startup);
+}
+
+// Takes the first trigger of the frame that switches the state
+fn run_state_machine(
+	machine: Res<StateMachine<GameplayState>>,
+	settings: Res<Settings>,
+	tuning: Res<Tuning>,
+	mut scoreboard: ResMut<Scoreboard>,
+	mut timer: ResMut<StateTimer>,
+	current_game_state: Res<State<GameplayState>>,
+	mut next_game_state: ResMut<NextState<GameplayState>>,
+	mut triggers: EventReader<Trigger>,
+	mut transition_events: EventWriter<TransitionEvent<GameplayState>>,
+) {
+	let conditions = Conditions {
+		is_browsing: settings.is_browsing,
+		is_decided: scoreboard.is_decided(),
+	};
+	let from = current_game_state.get();
+	let Some((trigger, to)) = triggers.read()
+		.find_map(|trigger| machine.next(from, *trigger, &conditions).map(|to| (*trigger, to.clone())))
+	else { return };
+	triggers.clear();
+
+	for action in machine.actions(&to) {
+		match action {
+			Action::StartTimer(delay) => {
+				timer.set_duration(delay.duration(&tuning));
+				timer.reset();
+				timer.unpause();
+			}
+			Action::ResetScoreboard => scoreboard.conceded = [0; 4],
+		}
+	}
+
+	info!("STATE: {from:?} -> {to:?} on {trigger:?}");
+	transition_events.send(TransitionEvent { from: from.clone(), to: to.clone(), trigger });
+	next_game_state.set(to);
+}
+
+fn tick_timer(
+	time: Res<Time>,
+	mut timer: ResMut<StateTimer>,
+	mut triggers: EventWriter<Trigger>,
+) {
+	timer.tick(time.delta());
+	if timer.just_finished()
+	{
+		timer.pause();
+		triggers.send(Trigger::Timeout);
+	}
+}
+
+fn accept_to_trigger(
+	mut accept_events: EventReader<AcceptEvent>,
+	mut triggers: EventWriter<Trigger>,
+) {
+	if accept_events.read().count() > 0
+	{
+		triggers.send(Trigger::Accept);
+	}
+}
+
+// Several balls may score within a frame, the set is over once
+fn goals_to_trigger(
+	mut goal_events: EventReader<GoalEvent>,
+	mut triggers: EventWriter<Trigger>,
+) {
+	if goal_events.read().filter(|goal| goal.ends_set).count() > 0 {
+		triggers.send(Trigger::SetOver);
+	}
+}
+
+fn eliminate_paddles(
+	mut commands: Commands,
+	scoreboard: Res<Scoreboard>,
+	mut query: Query<(Entity, &mut Visibility, &Side), (With<Paddle>, Without<Eliminated>)>,
+) {
+	// Keep paddles on the game over screen
+	if scoreboard.is_decided() { return }
+
+	for (entity, mut visibility, side) in &mut query {
+		if scoreboard.is_defending(*side) { continue }
+
+		*visibility = Visibility::Hidden;
+		commands.entity(entity).insert(Eliminated);
+	}
+}
+
+fn restore_paddles(
+	mut commands: Commands,
+	mut query: Query<(Entity, &mut Visibility), (With<Paddle>, With<Eliminated>)>,
+) {
+	for (entity, mut visibility) in &mut query {
+		*visibility = Visibility::Inherited;
+		commands.entity(entity).remove::<Eliminated>();
+	}
+}
+
+fn hide_ball(
+	mut ball_query: Query<&mut Visibility, With<Ball>>,
+) {
+	for mut ball_visibility in &mut ball_query {
+		*ball_visibility = Visibility::Hidden;
+	}
+}
+
+fn unhide_ball(
+	mut ball_query: Query<&mut Visibility, With<Ball>>,
+) {
+	for mut ball_visibility in &mut ball_query {
+		*ball_visibility = Visibility::Inherited;
+	}
+}
+
+// The machine alone, with the states of the match and with states a game may add
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::game::tests::{ball, conceded, press_accept, run_until, score_against, scoreboard_text, start_match, state, test_app};
+	use crate::game::Ball;
+	use crate::{Side, BALL_STARTING_SPEED, WIN_CONDITIONS};
+
+	#[derive(States, Debug, Clone, PartialEq, Eq, Hash)] enum Flow { Menu, Serve, Rally, Paused, Replay }
+
+	fn flow() -> StateMachine<Flow> {
+		StateMachine::new()
+			.with_transition(Flow::Menu,   Trigger::Accept,  Guard::Always,    Flow::Serve)
+			.with_transition(Flow::Serve,  Trigger::Timeout, Guard::Always,    Flow::Rally)
+			.with_transition(Flow::Rally,  Trigger::Pause,   Guard::Always,    Flow::Paused)
+			.with_transition(Flow::Paused, Trigger::Resume,  Guard::Always,    Flow::Rally)
+			.with_transition(Flow::Rally,  Trigger::SetOver, Guard::Undecided, Flow::Replay)
+			.with_transition(Flow::Rally,  Trigger::SetOver, Guard::Decided,   Flow::Menu)
+			.with_transition(Flow::Replay, Trigger::Timeout, Guard::Always,    Flow::Serve)
+			.with_action(Flow::Serve,  Action::StartTimer(Delay::Seconds(1.0)))
+			.with_action(Flow::Replay, Action::StartTimer(Delay::Seconds(3.0)))
+	}
+
+	const UNDECIDED: Conditions = Conditions { is_browsing: false, is_decided: false };
+	const DECIDED: Conditions   = Conditions { is_browsing: false, is_decided: true };
+
+	#[test]
+	fn match_follows_the_table() {
+		let machine = StateMachine::<GameplayState>::default();
+		let next = |from, trigger, conditions| machine.next(&from, trigger, &conditions).cloned();
+
+		assert_eq!(next(GameplayState::Startup, Trigger::Startup, UNDECIDED), Some(GameplayState::Instructions));
+		assert_eq!(next(GameplayState::Startup, Trigger::Startup, Conditions { is_browsing: true, ..UNDECIDED }), Some(GameplayState::Lobby));
+		assert_eq!(next(GameplayState::Instructions, Trigger::Accept, UNDECIDED), Some(GameplayState::Start));
+		assert_eq!(next(GameplayState::Start, Trigger::Timeout, UNDECIDED), Some(GameplayState::Active));
+		assert_eq!(next(GameplayState::Active, Trigger::SetOver, UNDECIDED), Some(GameplayState::NextSet));
+		assert_eq!(next(GameplayState::Active, Trigger::SetOver, DECIDED), Some(GameplayState::GameOver));
+		assert_eq!(next(GameplayState::NextSet, Trigger::Timeout, UNDECIDED), Some(GameplayState::Active));
+		assert_eq!(next(GameplayState::GameOver, Trigger::Accept, DECIDED), Some(GameplayState::Start));
+	}
+
+	#[test]
+	fn triggers_without_transition_keep_the_state() {
+		let machine = StateMachine::<GameplayState>::default();
+
+		assert_eq!(machine.next(&GameplayState::Active, Trigger::Accept, &UNDECIDED), None);
+		assert_eq!(machine.next(&GameplayState::Start, Trigger::SetOver, &UNDECIDED), None);
+		assert_eq!(machine.next(&GameplayState::Lobby, Trigger::Accept, &UNDECIDED), None);
+		assert_eq!(machine.next(&GameplayState::Instructions, Trigger::Timeout, &UNDECIDED), None);
+	}
+
+	#[test]
+	fn entering_start_resets_and_times() {
+		let machine = StateMachine::<GameplayState>::default();
+		let tuning = Tuning::default();
+
+		let actions: Vec<Action> = machine.actions(&GameplayState::Start).collect();
+		assert_eq!(actions, [Action::ResetScoreboard, Action::StartTimer(Delay::Start)]);
+		assert_eq!(Delay::Start.duration(&tuning), Duration::from_secs_f32(tuning.start_delay));
+		assert_eq!(machine.actions(&GameplayState::Active).count(), 0);
+	}
+
+	#[test]
+	fn other_states_pause_serve_and_replay() {
+		let machine = flow();
+		let mut state = Flow::Menu;
+		let mut step = |trigger, conditions| {
+			if let Some(next) = machine.next(&state, trigger, &conditions) { state = next.clone() }
+			state.clone()
+		};
+
+		assert_eq!(step(Trigger::Accept, UNDECIDED), Flow::Serve);
+		assert_eq!(step(Trigger::Pause, UNDECIDED), Flow::Serve);
+		assert_eq!(step(Trigger::Timeout, UNDECIDED), Flow::Rally);
+		assert_eq!(step(Trigger::Pause, UNDECIDED), Flow::Paused);
+		assert_eq!(step(Trigger::SetOver, UNDECIDED), Flow::Paused);
+		assert_eq!(step(Trigger::Resume, UNDECIDED), Flow::Rally);
+		assert_eq!(step(Trigger::SetOver, UNDECIDED), Flow::Replay);
+		assert_eq!(step(Trigger::Timeout, UNDECIDED), Flow::Serve);
+		assert_eq!(step(Trigger::Timeout, UNDECIDED), Flow::Rally);
+		assert_eq!(step(Trigger::SetOver, DECIDED), Flow::Menu);
+
+		assert_eq!(flow().actions(&Flow::Replay).collect::<Vec<_>>(), [Action::StartTimer(Delay::Seconds(3.0))]);
+	}
+
+	#[derive(Resource, Default)] struct Transitions(Vec<(GameplayState, GameplayState, Trigger)>);
+
+	fn record_transitions(
+		mut transition_events: EventReader<TransitionEvent<GameplayState>>,
+		mut transitions: ResMut<Transitions>,
+	) {
+		transitions.0.extend(transition_events.read().map(|event| (event.from.clone(), event.to.clone(), event.trigger)));
+	}
+
+	#[test]
+	fn startup_shows_instructions_until_accepted() {
+		let mut app = test_app();
+		assert_eq!(state(&app), GameplayState::Instructions);
+
+		for _ in 0..10 { app.update() }
+		assert_eq!(state(&app), GameplayState::Instructions);
+		assert_eq!(ball(&mut app).3, Visibility::Hidden);
+
+		press_accept(&mut app);
+		assert!(run_until(&mut app, GameplayState::Start));
+		assert_eq!(ball(&mut app).3, Visibility::Inherited);
+		assert!(!app.world.resource::<StateTimer>().finished());
+	}
+
+	#[test]
+	fn start_counts_down_to_serve() {
+		let mut app = test_app();
+		press_accept(&mut app);
+		assert!(run_until(&mut app, GameplayState::Start));
+
+		for _ in 0..10 { app.update() }
+		assert_eq!(state(&app), GameplayState::Start);
+		assert_eq!(ball(&mut app).1, Vec2::ZERO);
+
+		assert!(run_until(&mut app, GameplayState::Active));
+		let (_, velocity, _, _) = ball(&mut app);
+		assert!((velocity.length() - BALL_STARTING_SPEED).abs() < 1.0);
+	}
+
+	#[test]
+	fn last_goal_ends_game_and_accept_restarts() {
+		let mut app = test_app();
+		start_match(&mut app);
+
+		for goal in 1..WIN_CONDITIONS {
+			score_against(&mut app, Side::Right);
+			assert!(run_until(&mut app, GameplayState::NextSet));
+			assert_eq!(conceded(&app)[Side::Right as usize], goal);
+			assert!(run_until(&mut app, GameplayState::Active));
+		}
+		score_against(&mut app, Side::Right);
+		assert!(run_until(&mut app, GameplayState::GameOver));
+		assert_eq!(conceded(&app)[Side::Right as usize], WIN_CONDITIONS);
+		assert_eq!(ball(&mut app).3, Visibility::Hidden);
+
+		press_accept(&mut app);
+		assert!(run_until(&mut app, GameplayState::Start));
+		assert_eq!(conceded(&app), [0; 4]);
+		assert_eq!(scoreboard_text(&mut app), "0 0");
+		assert_eq!(ball(&mut app).1, Vec2::ZERO);
+	}
+
+	#[test]
+	fn match_without_a_ball_keeps_running() {
+		let mut app = test_app();
+		let mut query = app.world.query_filtered::<Entity, With<Ball>>();
+		for entity in query.iter(&app.world).collect::<Vec<_>>() {
+			app.world.despawn(entity);
+		}
+		start_match(&mut app);
+		for _ in 0..10 { app.update() }
+		assert_eq!(state(&app), GameplayState::Active);
+	}
+
+	#[test]
+	fn transitions_are_reported() {
+		let mut app = test_app();
+		app.init_resource::<Transitions>()
+			.add_systems(Update, record_transitions);
+		start_match(&mut app);
+		score_against(&mut app, Side::Left);
+		assert!(run_until(&mut app, GameplayState::NextSet));
+
+		assert_eq!(app.world.resource::<Transitions>().0, [
+			(GameplayState::Startup, GameplayState::Instructions, Trigger::Startup),
+			(GameplayState::Instructions, GameplayState::Start, Trigger::Accept),
+			(GameplayState::Start, GameplayState::Active, Trigger::Timeout),
+			(GameplayState::Active, GameplayState::NextSet, Trigger::SetOver),
+		]);
+	}
+}
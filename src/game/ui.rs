@@ -0,0 +1,507 @@
+// Texts on screen: paragraphs of every state, the scoreboard, the game over title, the lobby,
+// active power-ups, AI stats and the hold to exit prompt.
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::ecs::query::{QueryData, QueryFilter};
+
+// import crate
+use crate::game::{init_shared_resources, Paddle, Scoreboard, Settings, ZLAYER, GOLD_COLOR, RED_COLOR};
+use crate::game::ai::{AdaptiveAi, AiSkill};
+use crate::game::input::Player;
+use crate::game::physics::{power_ups_enabled, GoalEvent, PowerUps};
+use crate::game::state::{GameplayState, StateSet};
+use crate::{Arena, GameMode, Side, WIN_CONDITIONS};
+
+// Texts follow the state and the scoreboard after they were switched
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)] pub struct UiSet;
+
+pub const BASIC_TEXT_COLOR: Color   = Color::WHITE;
+const SCORE_TEXT_COLOR: Color       = Color::DARK_GRAY;
+const VICTORY_TEXT_COLOR: Color     = GOLD_COLOR;
+const DEFEAT_TEXT_COLOR: Color      = RED_COLOR;
+
+const HOLD_TO_EXIT: Duration = Duration::from_secs(2);
+
+pub const TEXT_RESOLUTION: f32    = 4.0;
+pub const GLOBAL_TEXT_SCALE: f32  = 1.0 / TEXT_RESOLUTION;
+const INSTRUCTIONS_FONT_SIZE: f32 = TEXT_RESOLUTION * 20.0;
+const INSTR_ICONS_FONT_SIZE: f32  = TEXT_RESOLUTION * 23.0;
+const START_FONT_SIZE: f32        = TEXT_RESOLUTION * 20.0;
+const SCORE_FONT_SIZE: f32        = TEXT_RESOLUTION * 300.0;
+const SCORE_FONT_SIZE_FOUR: f32   = TEXT_RESOLUTION * 100.0;
+const GAME_OVER_FONT_SIZE: f32    = TEXT_RESOLUTION * 60.0;
+pub const POWER_UP_FONT_SIZE: f32 = TEXT_RESOLUTION * 12.0;
+const AI_STATS_FONT_SIZE: f32     = TEXT_RESOLUTION * 12.0;
+
+pub const FONT_MEDIUM: &str = "embedded://fonts/openinghourssans.otf";
+const FONT_BOLD: &str       = "embedded://fonts/sundaymasthead.otf";
+const FONT_ICONS: &str      = "embedded://fonts/promptfont.otf";
+const FONT_SCORE: &str      = "embedded://fonts/basicallyamono-bold.otf";
+
+const KEYCODE_EXIT: KeyCode     = KeyCode::Escape;
+const KEYCODE_AI_STATS: KeyCode = KeyCode::F3;
+
+// Components
+#[derive(Component)] pub struct ScoreboardUi;
+#[derive(Component)] pub struct GameOverUi;
+#[derive(Component)] pub struct LobbyUi;
+#[derive(Component)] pub struct ExitUi;
+#[derive(Component)] pub struct AiStatsUi;
+#[derive(Component, Deref, DerefMut)] pub struct PowerUpUi(pub Side);
+#[derive(Component, Deref, DerefMut)] pub struct Paragraph { pub when_visible: GameplayState }
+
+// Bundles
+#[derive(Bundle)] pub struct ParagraphBundle {
+	pub information: Paragraph,
+	pub text_bundle: Text2dBundle,
+}
+impl ParagraphBundle {
+	pub fn new(state: GameplayState, position: Vec2, text: Text) -> Self {
+		Self {
+			information: Paragraph { when_visible: state },
+			text_bundle: Text2dBundle {
+				text,
+				visibility: Visibility::Hidden,
+				transform: Transform::from_xyz(position.x, position.y, ZLAYER::TEXT)
+				.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+				..default()
+			},
+		}
+	}
+}
+
+// Resources
+#[derive(Resource, Deref, DerefMut)] pub struct ExitTimer(Timer);
+
+pub struct UiPlugin;
+impl Plugin for UiPlugin {
+	fn build(&self, app: &mut App) {
+		init_shared_resources(app);
+
+		// Events
+		app.add_event::<GoalEvent>();
+
+		// Resources
+		app.insert_resource(ExitTimer(Timer::new(HOLD_TO_EXIT, TimerMode::Once)));
+
+		// Transitions
+		app.add_systems(OnExit(GameplayState::Instructions), unhide_scoreboard)
+			.add_systems(OnExit(GameplayState::Active), update_text_with_scoreboard)
+			.add_systems(OnEnter(GameplayState::GameOver), (
+				hide_scoreboard,
+				update_game_over,
+			))
+			.add_systems(OnExit(GameplayState::GameOver), (
+				update_text_with_scoreboard,
+				unhide_scoreboard,
+			));
+
+		// Systems: startup
+		app.add_systems(Startup, ui_setup);
+
+		// Systems: update
+		app.configure_sets(Update, UiSet.after(StateSet))
+			.add_systems(Update,
+				(
+				show_paragraphs.run_if(state_changed::<GameplayState>),
+				// extra balls score without leaving Active
+				update_text_with_scoreboard.run_if(on_event::<GoalEvent>()),
+				exit_on_esc,
+				update_power_up_ui.run_if(power_ups_enabled),
+				(toggle_ai_stats, update_ai_stats).chain().run_if(resource_exists::<AdaptiveAi>),
+				)
+				.in_set(UiSet)
+				);
+	}
+}
+
+// Fonts come from the embedded assets, headless apps without an AssetServer get the default font
+fn ui_setup(
+	mut commands: Commands,
+	asset_server: Option<Res<AssetServer>>,
+	settings: Res<Settings>,
+	arena: Res<Arena>,
+) {
+	let load_font = |path: &'static str| -> Handle<Font> {
+		asset_server.as_ref().map_or(Handle::default(), |asset_server| asset_server.load(path))
+	};
+
+	// Paragraphs
+	let font_medium = load_font(FONT_MEDIUM);
+	commands.spawn(ParagraphBundle::new(
+		GameplayState::Instructions,
+		Vec2::new(-80.0, 0.0),
+		Text::from_section("Movement\nSound\nFullscreen\nExit\n\nAccept",
+			TextStyle {
+				font: font_medium.clone(),
+				font_size: INSTRUCTIONS_FONT_SIZE,
+				color: BASIC_TEXT_COLOR })
+				.with_justify(JustifyText::Right),
+		));
+	commands.spawn(ParagraphBundle::new(
+		GameplayState::Instructions,
+		Vec2::new(50.0, 0.0),
+		Text::from_section("⏶⏷\n⑨⑩\n⑪\n␯\n\n␮",
+			TextStyle {
+				font: load_font(FONT_ICONS),
+				font_size: INSTR_ICONS_FONT_SIZE,
+				color: BASIC_TEXT_COLOR })
+				.with_justify(JustifyText::Right),
+		));
+	commands.spawn(ParagraphBundle::new(
+		GameplayState::Start,
+		Vec2::new(0.0, -160.0),
+		Text::from_section("Be ready!", TextStyle {
+			font: font_medium.clone(),
+			font_size: START_FONT_SIZE,
+			color: BASIC_TEXT_COLOR }),
+		));
+	commands.spawn((
+		GameOverUi,
+		ParagraphBundle::new(
+			GameplayState::GameOver,
+			Vec2::new(0.0, 0.0),
+			Text::from_section("", TextStyle {
+				font: load_font(FONT_BOLD),
+				font_size: GAME_OVER_FONT_SIZE,
+				color: BASIC_TEXT_COLOR }),
+		)));
+	if settings.is_browsing {
+		commands.spawn((
+			LobbyUi,
+			ParagraphBundle::new(
+				GameplayState::Lobby,
+				Vec2::new(0.0, 0.0),
+				Text::from_section("", TextStyle {
+					font: font_medium.clone(),
+					font_size: INSTRUCTIONS_FONT_SIZE,
+					color: BASIC_TEXT_COLOR })
+					.with_justify(JustifyText::Left),
+			)));
+	}
+
+	// Power-ups
+	if settings.power_ups {
+		for side in Side::ALL.into_iter().filter(|side| settings.controllers[*side as usize].is_some()) {
+			let position = side.paddle_position(&arena) * 0.75;
+			commands.spawn((
+				PowerUpUi(side),
+				Text2dBundle {
+					text:
+						Text::from_section("", TextStyle {
+						font: font_medium.clone(),
+						font_size: POWER_UP_FONT_SIZE,
+						color: GOLD_COLOR }),
+					transform:
+						Transform::from_xyz(position.x, position.y, ZLAYER::TEXT)
+						.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+					..default()
+				}));
+		}
+	}
+
+	// Scoreboard
+	let (score_text, score_font_size) = match settings.mode {
+		GameMode::Versus      => ("0 0".to_string(), SCORE_FONT_SIZE),
+		GameMode::FourPlayers => (format!(" {0} \n{0} {0}\n {0} ", WIN_CONDITIONS), SCORE_FONT_SIZE_FOUR),
+	};
+	commands.spawn((
+		ScoreboardUi,
+		Text2dBundle {
+			text:
+				Text::from_section(score_text, TextStyle {
+				font: load_font(FONT_SCORE),
+				font_size: score_font_size,
+				color: SCORE_TEXT_COLOR }),
+			transform:
+				Transform::from_xyz(0.0, 0.0, ZLAYER::SCORE)
+				.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+			visibility:
+				Visibility::Hidden,
+			..default()
+		}));
+
+	// Adaptive AI stats, toggled with F3
+	if settings.adaptive_ai.is_some() {
+		commands.spawn((
+			AiStatsUi,
+			Text2dBundle {
+				text:
+					Text::from_section("", TextStyle {
+					font: font_medium.clone(),
+					font_size: AI_STATS_FONT_SIZE,
+					color: BASIC_TEXT_COLOR })
+					.with_justify(JustifyText::Left),
+				text_anchor: bevy::sprite::Anchor::TopLeft,
+				transform:
+					Transform::from_xyz(-arena.size.x / 2.0 + 10.0, arena.size.y / 2.0 - 10.0, ZLAYER::TEXT)
+					.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+				visibility:
+					Visibility::Hidden,
+				..default()
+			}));
+	}
+
+	// Exit UI
+	commands.spawn((
+		ExitUi,
+		Text2dBundle {
+			text:
+				Text::from_section("Holding ESC to exit", TextStyle {
+				font: font_medium,
+				font_size: INSTRUCTIONS_FONT_SIZE,
+				color: BASIC_TEXT_COLOR }),
+			transform:
+				Transform::from_xyz(-210.0, 205.0, ZLAYER::TEXT)
+				.with_scale(Vec3::splat(GLOBAL_TEXT_SCALE)),
+			visibility:
+				Visibility::Hidden,
+			..default()
+		}));
+}
+
+// Set visibility for paragraphs
+fn show_paragraphs(
+	state: Res<State<GameplayState>>,
+	mut paragraph_query: Query<(&mut Visibility, &Paragraph)>,
+) {
+	for (mut p_visibility, paragraph) in &mut paragraph_query {
+		*p_visibility = match paragraph.when_visible == *state.get() {
+			true  => Visibility::Inherited,
+			false => Visibility::Hidden,
+		};
+	}
+}
+
+fn update_text_with_scoreboard(
+	settings: Res<Settings>,
+	scoreboard: Res<Scoreboard>,
+	mut query: Query<&mut Text, With<ScoreboardUi>>,
+) {
+	warn_if_missing(&query, "scoreboard");
+
+	let value = match settings.mode {
+		// Points of a side are goals conceded by its opponent
+		GameMode::Versus => format!("{} {}",
+			scoreboard.conceded[Side::Right as usize],
+			scoreboard.conceded[Side::Left as usize],
+		),
+		// Lives remaining, placed by side
+		GameMode::FourPlayers => format!(" {} \n{} {}\n {} ",
+			scoreboard.lives(Side::Top),
+			scoreboard.lives(Side::Left),
+			scoreboard.lives(Side::Right),
+			scoreboard.lives(Side::Bottom),
+		),
+	};
+	for mut text in &mut query {
+		let Some(section) = text.sections.first_mut() else { continue };
+		section.value.clone_from(&value);
+	}
+}
+
+fn hide_scoreboard(
+	mut query: Query<&mut Visibility, With<ScoreboardUi>>,
+) {
+	warn_if_missing(&query, "scoreboard");
+	for mut visibility in &mut query {
+		*visibility = Visibility::Hidden;
+	}
+}
+
+fn unhide_scoreboard(
+	mut query: Query<&mut Visibility, With<ScoreboardUi>>,
+) {
+	warn_if_missing(&query, "scoreboard");
+	for mut visibility in &mut query {
+		*visibility = Visibility::Inherited;
+	}
+}
+
+fn update_game_over(
+	scoreboard: Res<Scoreboard>,
+	paddle_query: Query<(&Side, Has<Player>), With<Paddle>>,
+	mut query: Query<&mut Text, With<GameOverUi>>
+) {
+	warn_if_missing(&query, "game over title");
+
+	let winner = scoreboard.survivors().next();
+	let players = paddle_query.iter().filter(|(_, is_player)| *is_player).count();
+	let is_victory = paddle_query.iter().any(|(side, is_player)| is_player && Some(*side) == winner);
+
+	let (color, value) = if players != 1 {
+		(VICTORY_TEXT_COLOR, match winner {
+			Some(side) => format!("{} WINS", side.name()),
+			None       => "DRAW".into(),
+		})
+	} else if is_victory {
+		(VICTORY_TEXT_COLOR, "VICTORY".into())
+	} else {
+		(DEFEAT_TEXT_COLOR, "DEFEAT".into())
+	};
+
+	for mut text in &mut query {
+		let Some(section) = text.sections.first_mut() else { continue };
+		section.style.color = color;
+		section.value.clone_from(&value);
+	}
+}
+
+fn update_power_up_ui(
+	paddle_query: Query<(&PowerUps, &Side), With<Paddle>>,
+	mut ui_query: Query<(&mut Text, &PowerUpUi)>,
+) {
+	for (mut text, ui_side) in &mut ui_query {
+		let Some((power_ups, _)) = paddle_query.iter().find(|(_, side)| **side == ui_side.0) else { continue };
+		let Some(section) = text.sections.first_mut() else { continue };
+
+		section.value = power_ups.iter()
+			.map(|(kind, timer)| format!("{} {:.0}", kind.name(), timer.remaining_secs().ceil()))
+			.collect::<Vec<_>>()
+			.join("\n");
+	}
+}
+
+fn toggle_ai_stats(
+	input: Res<ButtonInput<KeyCode>>,
+	mut query: Query<&mut Visibility, With<AiStatsUi>>,
+) {
+	if !input.just_pressed(KEYCODE_AI_STATS) { return }
+
+	for mut visibility in &mut query {
+		*visibility = match *visibility {
+			Visibility::Hidden => Visibility::Inherited,
+			_                  => Visibility::Hidden,
+		};
+	}
+}
+
+fn update_ai_stats(
+	adaptive: Res<AdaptiveAi>,
+	skill: Res<AiSkill>,
+	mut query: Query<&mut Text, With<AiStatsUi>>,
+) {
+	if !adaptive.is_changed() && !skill.is_changed() { return }
+
+	for mut text in &mut query {
+		let Some(section) = text.sections.first_mut() else { continue };
+		section.value = format!(
+			"AI level {:.2}\nTarget {:.0}%  Recent {:.0}%\nPoints won {}  lost {}\nReaction {:.2}s\nMax speed {:.0}\nAim error {:.0}",
+			adaptive.level, adaptive.target * 100.0, adaptive.recent_win_rate() * 100.0,
+			adaptive.won, adaptive.lost, skill.reaction_delay, skill.max_speed, skill.aim_error,
+		);
+	}
+}
+
+fn exit_on_esc(
+	mut exit: EventWriter<AppExit>,
+	mut exit_ui: Query<(&mut Visibility, &mut Text), With<ExitUi>>,
+	mut timer: ResMut<ExitTimer>,
+
+	input: Res<ButtonInput<KeyCode>>,
+	time: Res<Time<Real>>, // exits while the game is paused or slowed down
+) {
+	if input.just_released(KEYCODE_EXIT)
+	{
+		for (mut visibility, _) in &mut exit_ui {
+			*visibility = Visibility::Hidden;
+		}
+		timer.reset();
+		return;
+	}
+	if input.just_pressed(KEYCODE_EXIT)
+	{
+		warn_if_missing(&exit_ui, "exit prompt");
+		for (mut visibility, mut text) in &mut exit_ui {
+			*visibility = Visibility::Inherited;
+			let Some(section) = text.sections.first_mut() else { continue };
+			section.style.color.set_a(0.0);
+		}
+	}
+	if input.pressed(KEYCODE_EXIT)
+	{
+		let bezier = CubicSegment::new_bezier((0.85, 0.06), (0.34, 0.69));
+		for (_, mut text) in &mut exit_ui {
+			let Some(section) = text.sections.first_mut() else { continue };
+			section.style.color.set_a(bezier.ease(timer.fraction()) * 3.0);
+		}
+
+		timer.tick(time.delta());
+		if timer.just_finished() { exit.send(AppExit); }
+	}
+}
+
+// Texts may be missing while the UI is rebuilt or in apps without it, the game goes on without them
+fn warn_if_missing<D: QueryData, F: QueryFilter>(query: &Query<D, F>, name: &str) {
+	if query.is_empty() {
+		warn!("UI: no {name} to update");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::game::tests::{press, press_accept, run_until, score_against, scoreboard_text, start_match, state, test_app};
+	use crate::game::state::GameplayState;
+	use crate::game::{BallBundle, ExtraBall, Velocity};
+	use crate::{BALL_SIZE, BALL_STARTING_SPEED};
+
+	#[test]
+	fn missing_ui_does_not_stop_the_game() {
+		let mut app = test_app();
+		let mut query = app.world.query_filtered::<Entity, Or<(With<ScoreboardUi>, With<GameOverUi>, With<ExitUi>)>>();
+		for entity in query.iter(&app.world).collect::<Vec<_>>() {
+			app.world.despawn(entity);
+		}
+		press(&mut app, KeyCode::Escape);
+		start_match(&mut app);
+
+		for _ in 1..WIN_CONDITIONS {
+			score_against(&mut app, Side::Right);
+			assert!(run_until(&mut app, GameplayState::NextSet));
+			assert!(run_until(&mut app, GameplayState::Active));
+		}
+		score_against(&mut app, Side::Right);
+		assert!(run_until(&mut app, GameplayState::GameOver));
+		press_accept(&mut app);
+		assert!(run_until(&mut app, GameplayState::Start));
+	}
+
+	#[test]
+	fn extra_ball_goals_show_up_during_the_set() {
+		let mut app = test_app();
+		start_match(&mut app);
+
+		let arena = app.world.resource::<Arena>().clone();
+		let position = Vec3::new(arena.left_wall() + BALL_SIZE.x / 2.0 - 1.0, arena.top_wall() / 2.0, ZLAYER::BALL);
+		app.world.spawn((
+			BallBundle { velocity: Velocity(Side::Left.normal() * BALL_STARTING_SPEED), ..BallBundle::new(BALL_STARTING_SPEED) },
+			ExtraBall,
+			Transform::from_translation(position),
+			Visibility::Inherited,
+		));
+		app.update();
+		app.update();
+
+		assert_eq!(state(&app), GameplayState::Active);
+		assert_eq!(scoreboard_text(&mut app), "0 1");
+	}
+
+	#[test]
+	fn extra_scoreboards_all_follow_the_score() {
+		let mut app = test_app();
+		app.world.spawn((Text::from_section("", TextStyle::default()), Visibility::Hidden, ScoreboardUi));
+		start_match(&mut app);
+		score_against(&mut app, Side::Left);
+		assert!(run_until(&mut app, GameplayState::NextSet));
+
+		let mut query = app.world.query_filtered::<(&Text, &Visibility), With<ScoreboardUi>>();
+		let scoreboards: Vec<_> = query.iter(&app.world).map(|(text, visibility)| (text.sections[0].value.clone(), *visibility)).collect();
+		assert_eq!(scoreboards, vec![("0 1".to_string(), Visibility::Inherited); 2]);
+	}
+}
@@ -0,0 +1,609 @@
+// Gameplay shared by the game client and the dedicated server
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::math::bounding::{Aabb2d, BoundingVolume, IntersectsVolume, };
+
+pub mod bot;
+pub mod env;
+pub mod game;
+pub mod net;
+pub mod neural;
+pub mod protocol;
+pub mod simulation;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum CollisionH { Left, Right }
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum CollisionV { Top, Bottom }
+#[derive(Component, Debug, PartialEq, Eq, Copy, Clone)] pub enum Side { Left, Right, Bottom, Top }
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum GameMode { Versus, FourPlayers }
+
+pub const SIN_OF_45: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+pub const PADDLE_SIZE: Vec2     = Vec2::new(10.0, 90.0);
+pub const PADDLE_WALL_GAP: f32  = 20.0;
+
+pub const PLAYER_ACCELERATION: f32   = 2000.0;
+pub const PLAYER_DECELERATION: f32   = 2000.0;
+pub const PADDLE_FRICTION: f32       = 2000.0;
+pub const PLAYER_MAX_SPEED: f32      = 500.0;
+
+pub const BALL_SIZE: Vec2              = Vec2::new(10.0, 10.0);
+pub const BALL_STARTING_SPEED: f32     = 400.0;
+pub const BALL_DELTA_SPEED: f32        = 10.0;
+
+pub const FRAME_SIZE: Vec2 = Vec2::new(640.0, 480.0);
+
+pub const WALL_THICKNESS: f32         = 6.0;
+
+pub const START_DELAY: Duration     = Duration::from_secs(3);
+pub const NEXT_SET_DELAY: Duration  = Duration::from_secs(1);
+
+pub const WIN_CONDITIONS: u32 = 3;
+
+pub const PROJECTION_MARGIN: f32 = 40.0;
+
+// Largest angle from the paddle normal a ball leaves at, reached at the ends of the paddle
+pub const MAX_DEFLECTION: f32 = 50.0 * std::f32::consts::PI / 180.0;
+// Offensive AI keeps the ball this far within the ends of the paddle, as a share of half its length
+const AIM_OFFSET_LIMIT: f32 = 0.3;
+// Paddles ease in within this distance of where they head for
+const ARRIVAL_DISTANCE: f32 = PADDLE_SIZE.y / 8.0;
+
+#[derive(Resource, Debug, Clone)] pub struct Arena {
+	pub size: Vec2,
+	pub wall_thickness: f32,
+	pub goal_size: Option<f32>, // goal mouth in the middle of a side, whole side if None
+}
+
+// Goals conceded by each side, and whether a side takes part in the match
+#[derive(Resource)] pub struct Scoreboard { pub conceded: [u32; 4], pub in_play: [bool; 4] }
+
+// How a paddle reaches the velocity its controller asks for, the same for players and programs
+#[derive(Component, Debug, PartialEq, Copy, Clone)] pub struct PaddleMotion {
+	pub acceleration: f32, // speeding up in the direction of motion
+	pub deceleration: f32, // braking and turning around
+	pub friction: f32,     // slowing down without intent
+}
+
+impl Default for Arena {
+	fn default() -> Self {
+		Self { size: FRAME_SIZE, wall_thickness: WALL_THICKNESS, goal_size: None }
+	}
+}
+
+impl Arena {
+	pub fn left_wall(&self) -> f32   { -self.size.x / 2.0 }
+	pub fn right_wall(&self) -> f32  {  self.size.x / 2.0 }
+	pub fn bottom_wall(&self) -> f32 { -self.size.y / 2.0 + self.wall_thickness }
+	pub fn top_wall(&self) -> f32    {  self.size.y / 2.0 - self.wall_thickness }
+
+	// Distance from the center to the paddles of left/right and bottom/top sides
+	pub fn paddle_offset(&self) -> Vec2 {
+		Vec2::new(self.right_wall(), self.top_wall()) - PADDLE_WALL_GAP
+	}
+
+	// Half length of the wall of a side, between the perpendicular walls
+	pub fn half_wall_length(&self, side: Side) -> f32 {
+		match side {
+			Side::Left | Side::Right => self.top_wall(),
+			Side::Bottom | Side::Top => self.right_wall(),
+		}
+	}
+
+	// Whether a ball hitting the wall of the side at this position enters the goal
+	pub fn is_goal_mouth(&self, side: Side, position: Vec2) -> bool {
+		self.goal_size.is_none_or(|goal_size| position.dot(side.axis()).abs() <= goal_size / 2.0)
+	}
+
+	pub fn projection(&self) -> ScalingMode {
+		ScalingMode::AutoMin {
+			min_width: self.size.x + PROJECTION_MARGIN,
+			min_height: self.size.y + PROJECTION_MARGIN,
+		}
+	}
+}
+
+impl Scoreboard {
+	pub fn new(in_play: [bool; 4]) -> Self {
+		Self { conceded: [0; 4], in_play }
+	}
+
+	pub fn lives(&self, side: Side) -> u32 {
+		WIN_CONDITIONS.saturating_sub(self.conceded[side as usize])
+	}
+
+	// Wall of a defending side is a goal, otherwise it's solid
+	pub fn is_defending(&self, side: Side) -> bool {
+		self.in_play[side as usize] && self.lives(side) > 0
+	}
+
+	pub fn survivors(&self) -> impl Iterator<Item = Side> + '_ {
+		Side::ALL.into_iter().filter(|side| self.is_defending(*side))
+	}
+
+	// Side whose goal a ball reaching the walls at position scores in
+	pub fn goal(&self, walls: (Option<CollisionH>, Option<CollisionV>), position: Vec2, arena: &Arena) -> Option<Side> {
+		[walls.0.map(Side::from), walls.1.map(Side::from)]
+			.into_iter()
+			.flatten()
+			.find(|side| self.is_defending(*side) && arena.is_goal_mouth(*side, position))
+	}
+
+	pub fn concede(&mut self, side: Side) {
+		self.conceded[side as usize] += 1;
+	}
+
+	pub fn is_decided(&self) -> bool {
+		self.survivors().count() <= 1
+	}
+}
+
+impl Default for PaddleMotion {
+	fn default() -> Self {
+		Self { acceleration: PLAYER_ACCELERATION, deceleration: PLAYER_DECELERATION, friction: PADDLE_FRICTION }
+	}
+}
+
+impl PaddleMotion {
+	// Speed along the side axis after a step, intent is the share of max speed asked for from -1 to 1
+	pub fn step(&self, speed: f32, intent: f32, max_speed: f32, delta_seconds: f32) -> f32 {
+		let intent = intent.clamp(-1.0, 1.0);
+		let speed_goal = intent * max_speed;
+		let rate = if intent == 0.0 {
+			self.friction
+		} else if speed_goal * speed >= 0.0 && speed_goal.abs() > speed.abs() {
+			self.acceleration
+		} else {
+			self.deceleration
+		};
+
+		let max_delta = rate * delta_seconds;
+		(speed + (speed_goal - speed).clamp(-max_delta, max_delta)).clamp(-max_speed, max_speed)
+	}
+
+	// Intent bringing the paddle to rest at the target, braking early enough not to overshoot
+	pub fn intent_towards(&self, position: f32, speed: f32, target: f32) -> f32 {
+		let stopping_distance = speed * speed.abs() / (2.0 * self.deceleration);
+		let offset = target - position - stopping_distance;
+		(offset / ARRIVAL_DISTANCE).clamp(-1.0, 1.0)
+	}
+}
+
+impl Side {
+	pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Bottom, Side::Top];
+
+	// Direction along which a paddle of this side moves
+	pub fn axis(self) -> Vec2 {
+		match self {
+			Side::Left | Side::Right => Vec2::Y,
+			Side::Bottom | Side::Top => Vec2::X,
+		}
+	}
+
+	// Direction from the center of the arena towards the wall of this side
+	pub fn normal(self) -> Vec2 {
+		match self {
+			Side::Left   => Vec2::NEG_X,
+			Side::Right  => Vec2::X,
+			Side::Bottom => Vec2::NEG_Y,
+			Side::Top    => Vec2::Y,
+		}
+	}
+
+	pub fn paddle_size(self) -> Vec2 {
+		match self {
+			Side::Left | Side::Right => PADDLE_SIZE,
+			Side::Bottom | Side::Top => Vec2::new(PADDLE_SIZE.y, PADDLE_SIZE.x),
+		}
+	}
+
+	pub fn paddle_position(self, arena: &Arena) -> Vec2 {
+		self.normal() * arena.paddle_offset()
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Side::Left   => "LEFT",
+			Side::Right  => "RIGHT",
+			Side::Bottom => "BOTTOM",
+			Side::Top    => "TOP",
+		}
+	}
+}
+
+impl From<CollisionH> for Side {
+	fn from(collision: CollisionH) -> Self {
+		match collision {
+			CollisionH::Left  => Side::Left,
+			CollisionH::Right => Side::Right,
+		}
+	}
+}
+
+impl From<CollisionV> for Side {
+	fn from(collision: CollisionV) -> Self {
+		match collision {
+			CollisionV::Top    => Side::Top,
+			CollisionV::Bottom => Side::Bottom,
+		}
+	}
+}
+
+impl GameMode {
+	// Paddles can't cross the center of a side further than this
+	pub fn paddle_bound(self, side: Side, arena: &Arena) -> f32 {
+		self.sized_paddle_bound(side, arena, PADDLE_SIZE)
+	}
+
+	// Same for paddles of another size, given as width and length of the left paddle
+	pub fn sized_paddle_bound(self, side: Side, arena: &Arena, size: Vec2) -> f32 {
+		let offset = arena.paddle_offset();
+		match (self, side) {
+			(GameMode::Versus, _) => arena.top_wall() - size.y / 2.0,
+			(GameMode::FourPlayers, Side::Left | Side::Right) => offset.y - (size.x + size.y) / 2.0,
+			(GameMode::FourPlayers, Side::Bottom | Side::Top) => offset.x - (size.x + size.y) / 2.0,
+		}
+	}
+}
+
+pub fn collide_with_walls(ball: Aabb2d, arena: &Arena) -> (Option<CollisionH>, Option<CollisionV>)
+{
+	let mut side = (None, None);
+	if (ball.center().x - ball.half_size().x) <= arena.left_wall() { side.0 = Some(CollisionH::Left); }
+	if (ball.center().x + ball.half_size().x) >= arena.right_wall() { side.0 = Some(CollisionH::Right); }
+
+	if (ball.center().y - ball.half_size().y) <= arena.bottom_wall() { side.1 = Some(CollisionV::Bottom); }
+	if (ball.center().y + ball.half_size().y) >= arena.top_wall() { side.1 = Some(CollisionV::Top); }
+
+	side
+}
+
+pub fn collide_with_collider(ball: Aabb2d, collider: Aabb2d) -> (Option<CollisionH>, Option<CollisionV>)
+{
+	if !ball.intersects(&collider) {
+		return (None, None);
+	}
+
+	// the ball is pushed back along the axis it entered the least, also once its center is inside
+	let overlap = ball.max.min(collider.max) - ball.min.max(collider.min);
+	let offset = ball.center() - collider.center(); // offset of the ball relative to the collider
+	if overlap.x < overlap.y {
+		if offset.x < 0. {
+			(Some(CollisionH::Right), None)
+		} else {
+			(Some(CollisionH::Left), None)
+		}
+	} else if offset.y > 0. {
+		(None, Some(CollisionV::Bottom))
+	} else {
+		(None, Some(CollisionV::Top))
+	}
+}
+
+// Velocity turned back from the sides of a collision it heads into, speed is kept
+pub fn reflect(velocity: Vec2, collision: (Option<CollisionH>, Option<CollisionV>)) -> Vec2
+{
+	let mut velocity = velocity;
+	let reflect_x = match collision.0 {
+		Some(CollisionH::Left)  => velocity.x < 0.0,
+		Some(CollisionH::Right) => velocity.x > 0.0,
+		None => false,
+	};
+	if reflect_x { velocity.x = -velocity.x; }
+
+	let reflect_y = match collision.1 {
+		Some(CollisionV::Top)    => velocity.y > 0.0,
+		Some(CollisionV::Bottom) => velocity.y < 0.0,
+		None => false,
+	};
+	if reflect_y { velocity.y = -velocity.y; }
+
+	velocity
+}
+
+// Center of the ball moved back within the walls
+pub fn separate_from_walls(ball: Aabb2d, arena: &Arena) -> Vec2
+{
+	let min = Vec2::new(arena.left_wall(), arena.bottom_wall()) + ball.half_size();
+	let max = Vec2::new(arena.right_wall(), arena.top_wall()) - ball.half_size();
+	ball.center().clamp(min, max)
+}
+
+// Center of the ball moved out of the collider through the sides of the collision
+fn separate_from_collider(ball: Aabb2d, collider: Aabb2d, collision: (Option<CollisionH>, Option<CollisionV>)) -> Vec2
+{
+	let mut center = ball.center();
+	match collision.0 {
+		Some(CollisionH::Left)  => center.x = collider.max.x + ball.half_size().x,
+		Some(CollisionH::Right) => center.x = collider.min.x - ball.half_size().x,
+		None => (),
+	}
+	match collision.1 {
+		Some(CollisionV::Top)    => center.y = collider.min.y - ball.half_size().y,
+		Some(CollisionV::Bottom) => center.y = collider.max.y + ball.half_size().y,
+		None => (),
+	}
+	center
+}
+
+// Velocity of a ball leaving the paddle of a side, steered by where it hit the paddle:
+// offset runs from -1 to 1 between the ends of the paddle along the side axis
+pub fn deflect(side: Side, speed: f32, offset: f32) -> Vec2 {
+	let angle = offset.clamp(-1.0, 1.0) * MAX_DEFLECTION;
+	(side.axis() * angle.sin() - side.normal() * angle.cos()) * speed
+}
+
+// Distance from the center along the normal of a side at which the ball touches its paddle,
+// paddle_size as the paddle stands in the arena
+fn paddle_contact(side: Side, paddle_size: Vec2, arena: &Arena) -> f32 {
+	side.paddle_position(arena).dot(side.normal()) - (paddle_size.dot(side.normal().abs()) + BALL_SIZE.x) / 2.0
+}
+
+// Position along the side axis where the ball reaches the paddle and the seconds it takes,
+// bouncing off the walls on the way; None when the ball isn't approaching
+pub fn predict_intercept(side: Side, position: Vec2, velocity: Vec2, paddle_size: Vec2, arena: &Arena) -> Option<(f32, f32)> {
+	let speed = velocity.dot(side.normal());
+	if speed <= 0.0 { return None }
+	let time = (paddle_contact(side, paddle_size, arena) - position.dot(side.normal())) / speed;
+	if time < 0.0 { return None }
+
+	// bounces mirror the straight path back between the walls
+	let bound = arena.half_wall_length(side) - BALL_SIZE.x / 2.0;
+	let straight = position.dot(side.axis()) + velocity.dot(side.axis()) * time;
+	let folded = (straight + bound).rem_euclid(4.0 * bound);
+	let along = if folded <= 2.0 * bound { folded - bound } else { 3.0 * bound - folded };
+	Some((along, time))
+}
+
+// Paddle position along the side axis that sends the ball to the end of the opposite wall farthest
+// from the opponent, None if the paddle can't get there in time
+pub fn aim_away(side: Side, paddle: f32, paddle_size: Vec2, max_speed: f32, ball: (Vec2, Vec2), opponent: f32, arena: &Arena) -> Option<f32> {
+	let (intercept, time) = predict_intercept(side, ball.0, ball.1, paddle_size, arena)?;
+
+	let bound = arena.half_wall_length(side) - BALL_SIZE.x;
+	let target = if opponent > 0.0 { -bound } else { bound };
+	let direction = Vec2::new(2.0 * paddle_contact(side, paddle_size, arena), target - intercept); // across the arena, along the axis
+	let offset = (direction.y.atan2(direction.x) / MAX_DEFLECTION).clamp(-AIM_OFFSET_LIMIT, AIM_OFFSET_LIMIT);
+
+	let position = intercept - offset * paddle_size.dot(side.axis().abs()) / 2.0;
+	((position - paddle).abs() <= max_speed * time).then_some(position)
+}
+
+// Position along the side axis the built-in AI heads for, before its aim error: offensive AI, given the
+// opponent, aims at the intercept away from it once it can make it, otherwise the paddle lines up with the ball
+pub fn ai_target(side: Side, paddle: f32, paddle_size: Vec2, max_speed: f32, ball: (Vec2, Vec2), opponent: Option<f32>, arena: &Arena) -> f32 {
+	opponent
+		.and_then(|opponent| aim_away(side, paddle, paddle_size, max_speed, ball, opponent, arena))
+		.unwrap_or(ball.0.dot(side.axis()))
+}
+
+// Collision of the ball with a collider and its center moved out of it; a ball with no room left
+// between the collider and a wall is pushed out along the other axis
+pub fn bounce_off_collider(ball: Aabb2d, collider: Aabb2d, arena: &Arena) -> ((Option<CollisionH>, Option<CollisionV>), Vec2)
+{
+	let collision = collide_with_collider(ball, collider);
+	let center = separate_from_collider(ball, collider, collision);
+	if separate_from_walls(Aabb2d::new(center, ball.half_size()), arena) == center {
+		return (collision, center);
+	}
+
+	let offset = ball.center() - collider.center();
+	let other = match collision {
+		(Some(_), None) => (None, Some(if offset.y > 0. { CollisionV::Bottom } else { CollisionV::Top })),
+		(None, Some(_)) => (Some(if offset.x < 0. { CollisionH::Right } else { CollisionH::Left }), None),
+		_ => return (collision, center),
+	};
+	(other, separate_from_collider(ball, collider, other))
+}
+
+// Something the ball bounces off, paddles name the side they defend
+#[derive(Debug, Copy, Clone)] pub struct BallCollider { pub aabb: Aabb2d, pub paddle: Option<Side> }
+
+// Ball after one step against the walls and colliders
+#[derive(Debug, Default, Clone)] pub struct Bounce {
+	pub position: Vec2,
+	pub velocity: Vec2,
+	pub walls: (Option<CollisionH>, Option<CollisionV>), // walls reached before the ball was moved back
+	pub hits: Vec<(usize, Vec2)>, // colliders hit, with the ball position once moved out of each
+}
+
+impl Bounce {
+	pub fn is_collision(&self) -> bool {
+		self.walls.0.is_some() || self.walls.1.is_some() || !self.hits.is_empty()
+	}
+}
+
+// One step of a ball already moved to its new position, shared by the game and the simulation:
+// the ball is moved back within the walls and out of every collider in turn, then reflected,
+// and steered by the last paddle face it hit
+pub fn bounce_ball(position: Vec2, velocity: Vec2, colliders: &[BallCollider], arena: &Arena) -> Bounce
+{
+	let walls = collide_with_walls(Aabb2d::new(position, BALL_SIZE / 2.0), arena);
+	let mut position = separate_from_walls(Aabb2d::new(position, BALL_SIZE / 2.0), arena);
+	let mut collision = walls;
+	let mut hits = Vec::new();
+	let mut deflection = None;
+
+	for (index, collider) in colliders.iter().enumerate() {
+		let (collider_collision, separated) = bounce_off_collider(Aabb2d::new(position, BALL_SIZE / 2.0), collider.aabb, arena);
+		if collider_collision.0.is_none() && collider_collision.1.is_none() { continue }
+
+		position = separated;
+		collision.0 = collider_collision.0.or(collision.0);
+		collision.1 = collider_collision.1.or(collision.1);
+		hits.push((index, position));
+
+		// hits on the face steer the ball by where they land
+		let Some(side) = collider.paddle else { continue };
+		let is_face = match side {
+			Side::Left | Side::Right => collider_collision.0.is_some(),
+			Side::Bottom | Side::Top => collider_collision.1.is_some(),
+		};
+		if is_face && velocity.dot(side.normal()) > 0.0 {
+			let offset = (position - collider.aabb.center()).dot(side.axis());
+			deflection = Some((side, offset / collider.aabb.half_size().dot(side.axis())));
+		}
+	}
+
+	let mut velocity = reflect(velocity, collision);
+	if let Some((side, offset)) = deflection {
+		velocity = deflect(side, velocity.length(), offset);
+	}
+	Bounce { position, velocity, walls, hits }
+}
+
+// Ball after a collision: max speed raised by delta and the velocity brought up to it
+pub fn speed_up(velocity: Vec2, max_speed: f32, delta: f32) -> (Vec2, f32) {
+	let max_speed = max_speed + delta;
+	(velocity.clamp_length_min(max_speed), max_speed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	const EPSILON: f32 = 1e-3;
+
+	// Paddles of a versus match standing still at positions along their side
+	fn paddles(arena: &Arena, positions: [f32; 2]) -> [BallCollider; 2] {
+		let bound = GameMode::Versus.paddle_bound(Side::Left, arena);
+		[Side::Left, Side::Right].map(|side| {
+			let position = positions[side as usize].clamp(-bound, bound);
+			let aabb = Aabb2d::new(side.paddle_position(arena) + side.axis() * position, PADDLE_SIZE / 2.0);
+			BallCollider { aabb, paddle: Some(side) }
+		})
+	}
+
+	// Ball overlapping the collider by more than rounding
+	fn is_inside(ball: Aabb2d, collider: Aabb2d) -> bool {
+		let overlap = ball.max.min(collider.max) - ball.min.max(collider.min);
+		overlap.x > EPSILON && overlap.y > EPSILON
+	}
+
+	fn is_within_walls(aabb: Aabb2d, arena: &Arena) -> bool {
+		aabb.min.x >= arena.left_wall() - EPSILON && aabb.max.x <= arena.right_wall() + EPSILON
+			&& aabb.min.y >= arena.bottom_wall() - EPSILON && aabb.max.y <= arena.top_wall() + EPSILON
+	}
+
+	// One frame of the ball moved by its velocity, then bounced as in the game and the simulation
+	fn step(position: Vec2, velocity: Vec2, paddles: &[BallCollider], dt: f32, arena: &Arena) -> Bounce {
+		bounce_ball(position + velocity * dt, velocity, paddles, arena)
+	}
+
+	fn arena_position() -> impl Strategy<Value = Vec2> {
+		let arena = Arena::default();
+		let half_size = Vec2::new(arena.right_wall(), arena.top_wall()) - BALL_SIZE / 2.0;
+		(-half_size.x..=half_size.x, -half_size.y..=half_size.y).prop_map(|(x, y)| Vec2::new(x, y))
+	}
+
+	fn velocity() -> impl Strategy<Value = Vec2> {
+		(0.0..std::f32::consts::TAU, 0.0f32..1000.0).prop_map(|(angle, speed)| Vec2::from_angle(angle) * speed)
+	}
+
+	proptest! {
+		#[test]
+		fn walls_hold_the_ball(position in arena_position(), velocity in velocity(), dt in 0.0f32..0.05) {
+			let arena = Arena::default();
+			let position = step(position, velocity, &[], dt, &arena).position;
+			prop_assert!(is_within_walls(Aabb2d::new(position, BALL_SIZE / 2.0), &arena), "ball left the arena at {position}");
+		}
+
+		#[test]
+		fn ball_never_ends_inside_a_paddle(
+			position in arena_position(),
+			velocity in velocity(),
+			paddle_positions in (-200.0f32..200.0, -200.0f32..200.0),
+			dt in 0.0f32..0.05,
+		) {
+			let arena = Arena::default();
+			let paddles = paddles(&arena, [paddle_positions.0, paddle_positions.1]);
+			prop_assume!(paddles.iter().all(|paddle| !is_inside(Aabb2d::new(position, BALL_SIZE / 2.0), paddle.aabb)));
+
+			let position = step(position, velocity, &paddles, dt, &arena).position;
+			let ball = Aabb2d::new(position, BALL_SIZE / 2.0);
+			prop_assert!(paddles.iter().all(|paddle| !is_inside(ball, paddle.aabb)), "ball ended inside a paddle at {position}");
+			prop_assert!(is_within_walls(ball, &arena), "ball left the arena at {position}");
+		}
+
+		#[test]
+		fn reflections_keep_speed(
+			position in arena_position(),
+			velocity in velocity(),
+			paddle_positions in (-200.0f32..200.0, -200.0f32..200.0),
+			dt in 0.0f32..0.05,
+		) {
+			let arena = Arena::default();
+			let paddles = paddles(&arena, [paddle_positions.0, paddle_positions.1]);
+			let reflected = step(position, velocity, &paddles, dt, &arena).velocity;
+			prop_assert!((reflected.length() - velocity.length()).abs() <= EPSILON * velocity.length().max(1.0));
+		}
+
+		#[test]
+		fn deflections_keep_speed(speed in 0.0f32..1000.0, offset in -2.0f32..2.0) {
+			for side in Side::ALL {
+				let velocity = deflect(side, speed, offset);
+				prop_assert!((velocity.length() - speed).abs() <= EPSILON * speed.max(1.0));
+				prop_assert!(velocity.dot(side.normal()) <= 0.0, "deflected ball heads into the {} wall", side.name());
+			}
+		}
+
+		#[test]
+		fn paddle_motion_keeps_limits(speed in -500.0f32..500.0, intent in -2.0f32..2.0, dt in 0.0f32..0.05) {
+			let motion = PaddleMotion { acceleration: 2000.0, deceleration: 3000.0, friction: 1000.0 };
+			let new_speed = motion.step(speed, intent, PLAYER_MAX_SPEED, dt);
+			prop_assert!(new_speed.abs() <= PLAYER_MAX_SPEED);
+			prop_assert!((new_speed - speed).abs() <= motion.deceleration * dt + EPSILON);
+		}
+	}
+
+	#[test]
+	fn paddle_motion_uses_the_rate_of_the_change() {
+		let motion = PaddleMotion { acceleration: 1000.0, deceleration: 4000.0, friction: 500.0 };
+		let step = |speed, intent| motion.step(speed, intent, PLAYER_MAX_SPEED, 0.01);
+
+		assert!((step(0.0, 1.0) - 10.0).abs() < EPSILON);    // speeding up
+		assert!((step(200.0, -1.0) - 160.0).abs() < EPSILON); // turning around
+		assert!((step(200.0, 0.2) - 160.0).abs() < EPSILON);  // braking to a lower speed
+		assert!((step(200.0, 0.0) - 195.0).abs() < EPSILON);  // coasting
+	}
+
+	#[test]
+	fn intent_towards_settles_at_the_target() {
+		let motion = PaddleMotion::default();
+		let dt = 1.0 / 60.0;
+		let (mut position, mut speed) = (-150.0, 0.0);
+		let mut farthest = position;
+
+		for _ in 0..180 {
+			speed = motion.step(speed, motion.intent_towards(position, speed, 100.0), PLAYER_MAX_SPEED, dt);
+			position += speed * dt;
+			farthest = f32::max(farthest, position);
+		}
+		assert!((position - 100.0).abs() < 1.0, "rested at {position}");
+		assert!(farthest < 100.0 + ARRIVAL_DISTANCE, "overshot to {farthest}");
+	}
+
+	#[test]
+	fn aiming_follows_the_size_of_the_paddle() {
+		let arena = Arena::default();
+		let ball = (Vec2::new(-100.0, 0.0), Vec2::new(300.0, 50.0));
+		let enlarged = PADDLE_SIZE * Vec2::new(2.0, 1.5);
+
+		// a thicker paddle meets the ball sooner
+		let (_, time) = predict_intercept(Side::Right, ball.0, ball.1, PADDLE_SIZE, &arena).unwrap();
+		let (_, sooner) = predict_intercept(Side::Right, ball.0, ball.1, enlarged, &arena).unwrap();
+		assert!((time - sooner - PADDLE_SIZE.x / 2.0 / ball.1.x).abs() < EPSILON, "{time} and {sooner}");
+
+		// a longer paddle is moved further off the intercept for the same deflection
+		let (intercept, _) = predict_intercept(Side::Right, ball.0, ball.1, PADDLE_SIZE, &arena).unwrap();
+		let aim = |size| aim_away(Side::Right, intercept, size, PLAYER_MAX_SPEED, ball, 100.0, &arena).unwrap() - intercept;
+		let longer = PADDLE_SIZE * Vec2::new(1.0, 1.5);
+		assert!(aim(PADDLE_SIZE).abs() > 1.0);
+		assert!((aim(longer) - aim(PADDLE_SIZE) * 1.5).abs() < EPSILON * 100.0, "{} and {}", aim(longer), aim(PADDLE_SIZE));
+	}
+}
@@ -0,0 +1,516 @@
+// Peer to peer versus over UDP.
+// Every tick each peer sends its recent inputs; remote inputs that are not there yet are
+// predicted, and the simulation is rewound and replayed when a prediction turns out wrong.
+
+// import std
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+// import bevy
+use bevy::prelude::*;
+
+// import crate
+use crate::{Arena, Side, WIN_CONDITIONS};
+use crate::protocol::{Advert, CONNECTION_TIMEOUT, DISCOVERY_PORT};
+use crate::simulation::{PaddleInput, Simulation, StepEvents, SIDES, TICK_RATE};
+
+// Ticks the simulation may run ahead of the last confirmed remote input
+pub const MAX_PREDICTION: u32 = 8;
+// Local inputs repeated in every packet to survive packet loss
+const INPUT_HISTORY: u32 = 32;
+// Ticks of confirmed checksums kept to compare with late reports of the peer
+const CHECKSUM_HISTORY: u32 = 256;
+
+const PACKET_MAGIC: u8 = b'P';
+const NO_CHECKSUM: u32 = u32::MAX;
+
+// Ticks without packets after which a connected peer is considered gone
+const PEER_TIMEOUT: u32 = (CONNECTION_TIMEOUT * TICK_RATE as f32) as u32;
+
+// Peer address of a host that takes the first player to join, e.g. from the lobby
+pub const ANY_PEER: &str = "any";
+const ADVERT_NAME: &str    = "Peer to peer";
+const ADVERT_INTERVAL: u32 = TICK_RATE as u32; // ticks
+
+// Why a session stopped, the match can't go on after either
+#[derive(Debug, PartialEq, Eq, Copy, Clone)] pub enum SessionEnd { Desync(u32), PeerLeft }
+
+#[derive(Debug, Clone, PartialEq)] pub struct Packet {
+	pub first_tick: u32,
+	pub inputs: Vec<PaddleInput>,
+	pub ack: u32,                       // remote inputs received without gaps
+	pub checksum: Option<(u32, u64)>,   // state checksum after a confirmed tick
+}
+
+impl Packet {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(22 + self.inputs.len());
+		bytes.push(PACKET_MAGIC);
+		bytes.extend(self.first_tick.to_le_bytes());
+		bytes.push(self.inputs.len() as u8);
+		bytes.extend(self.inputs.iter().map(|input| input.0));
+		bytes.extend(self.ack.to_le_bytes());
+		let (tick, checksum) = self.checksum.unwrap_or((NO_CHECKSUM, 0));
+		bytes.extend(tick.to_le_bytes());
+		bytes.extend(checksum.to_le_bytes());
+		bytes
+	}
+
+	pub fn decode(bytes: &[u8]) -> Option<Self> {
+		let (&magic, bytes) = bytes.split_first()?;
+		if magic != PACKET_MAGIC { return None }
+
+		let first_tick = u32::from_le_bytes(bytes.get(0..4)?.try_into().ok()?);
+		let count = usize::from(*bytes.get(4)?);
+		let inputs = bytes.get(5..5 + count)?.iter().map(|bits| PaddleInput::from_bits(*bits)).collect::<Option<_>>()?;
+		let rest = bytes.get(5 + count..)?;
+
+		let ack = u32::from_le_bytes(rest.get(0..4)?.try_into().ok()?);
+		let checksum_tick = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?);
+		let checksum = u64::from_le_bytes(rest.get(8..16)?.try_into().ok()?);
+
+		Some(Self {
+			first_tick,
+			inputs,
+			ack,
+			checksum: (checksum_tick != NO_CHECKSUM).then_some((checksum_tick, checksum)),
+		})
+	}
+}
+
+// Input bookkeeping and resimulation, independent of the transport
+pub struct Rollback {
+	local: usize, // index of the local paddle in the simulation
+	input_delay: u32,
+	arena: Arena,
+	simulation: Simulation,
+	local_inputs: BTreeMap<u32, PaddleInput>,
+	remote_inputs: BTreeMap<u32, PaddleInput>,
+	predicted: BTreeMap<u32, PaddleInput>,  // remote input each simulated tick used
+	snapshots: BTreeMap<u32, Simulation>,   // state before each unconfirmed tick
+	checksums: BTreeMap<u32, u64>,          // state after each confirmed tick
+	remote_confirmed: u32,                  // remote inputs known without gaps
+	rollback_from: Option<u32>,
+	desync: Option<u32>,
+}
+
+impl Rollback {
+	pub fn new(local: usize, input_delay: u32, arena: Arena) -> Self {
+		Self {
+			local,
+			input_delay,
+			arena,
+			simulation: Simulation::default(),
+			local_inputs: BTreeMap::new(),
+			remote_inputs: BTreeMap::new(),
+			predicted: BTreeMap::new(),
+			snapshots: BTreeMap::new(),
+			checksums: BTreeMap::new(),
+			remote_confirmed: 0,
+			rollback_from: None,
+			desync: None,
+		}
+	}
+
+	pub fn simulation(&self) -> &Simulation {
+		&self.simulation
+	}
+
+	// Input pressed now is applied after the configured delay
+	pub fn add_local_input(&mut self, input: PaddleInput) {
+		self.local_inputs.insert(self.simulation.tick + self.input_delay, input);
+	}
+
+	pub fn add_remote_input(&mut self, tick: u32, input: PaddleInput) {
+		if self.remote_inputs.contains_key(&tick) || tick < self.remote_confirmed { return }
+		self.remote_inputs.insert(tick, input);
+
+		if self.predicted.get(&tick).is_some_and(|predicted| *predicted != input) {
+			self.rollback_from = Some(self.rollback_from.map_or(tick, |from| from.min(tick)));
+		}
+		while self.remote_inputs.contains_key(&self.remote_confirmed) {
+			self.remote_confirmed += 1;
+		}
+	}
+
+	pub fn can_advance(&self) -> bool {
+		self.simulation.tick < self.remote_confirmed + MAX_PREDICTION
+	}
+
+	// Fix mispredictions, then simulate the next tick unless too far ahead of the peer
+	pub fn advance(&mut self) -> Option<StepEvents> {
+		if let Some(from) = self.rollback_from.take() {
+			self.resimulate(from);
+		}
+
+		let events = self.can_advance().then(|| self.step());
+		self.confirm();
+		events
+	}
+
+	// First confirmed tick the peers disagree on
+	pub fn desync(&self) -> Option<u32> {
+		self.desync
+	}
+
+	// Latest confirmed tick and the checksum of the state after it
+	pub fn latest_checksum(&self) -> Option<(u32, u64)> {
+		self.checksums.last_key_value().map(|(tick, checksum)| (*tick, *checksum))
+	}
+
+	pub fn check_remote_checksum(&mut self, tick: u32, checksum: u64) {
+		let Some(local) = self.checksums.get(&tick) else { return };
+		if *local != checksum && self.desync.is_none() {
+			error!("DESYNC: tick {tick}, local {local:016x}, remote {checksum:016x}");
+			self.desync = Some(tick);
+		}
+	}
+
+	pub fn packet(&self, remote_ack: u32) -> Packet {
+		let last = self.simulation.tick + self.input_delay;
+		let first = remote_ack.max(last.saturating_sub(INPUT_HISTORY));
+		Packet {
+			first_tick: first,
+			inputs: (first..last).map(|tick| self.local_input(tick)).collect(),
+			ack: self.remote_confirmed,
+			checksum: self.latest_checksum(),
+		}
+	}
+
+	fn local_input(&self, tick: u32) -> PaddleInput {
+		self.local_inputs.get(&tick).copied().unwrap_or_default()
+	}
+
+	// Confirmed input, or the last one known before it
+	fn remote_input(&self, tick: u32) -> PaddleInput {
+		self.remote_inputs.range(..=tick).next_back().map(|(_, input)| *input).unwrap_or_default()
+	}
+
+	fn step(&mut self) -> StepEvents {
+		let tick = self.simulation.tick;
+		let remote = self.remote_input(tick);
+		self.snapshots.insert(tick, self.simulation.clone());
+		self.predicted.insert(tick, remote);
+
+		let mut inputs = [remote; 2];
+		inputs[self.local] = self.local_input(tick);
+		self.simulation.step(inputs, &self.arena)
+	}
+
+	fn resimulate(&mut self, from: u32) {
+		let Some(snapshot) = self.snapshots.get(&from) else { return };
+		let current = self.simulation.tick;
+		self.simulation = snapshot.clone();
+
+		while self.simulation.tick < current {
+			self.step();
+		}
+	}
+
+	// Ticks simulated with confirmed inputs on both sides won't change anymore
+	fn confirm(&mut self) {
+		let confirmed = self.remote_confirmed.min(self.simulation.tick);
+		let first = self.checksums.last_key_value().map_or(0, |(tick, _)| tick + 1);
+
+		for tick in first..confirmed {
+			let after = match self.snapshots.get(&(tick + 1)) {
+				Some(snapshot) => snapshot.checksum(),
+				None           => self.simulation.checksum(),
+			};
+			self.checksums.insert(tick, after);
+		}
+
+		self.snapshots = self.snapshots.split_off(&confirmed);
+		self.predicted = self.predicted.split_off(&confirmed);
+		self.remote_inputs = self.remote_inputs.split_off(&confirmed.saturating_sub(1));
+		self.local_inputs = self.local_inputs.split_off(&confirmed.saturating_sub(INPUT_HISTORY));
+		self.checksums = self.checksums.split_off(&confirmed.saturating_sub(CHECKSUM_HISTORY));
+	}
+}
+
+#[derive(Resource)] pub struct NetSession {
+	socket: UdpSocket,
+	peer: Option<SocketAddr>, // None until the first player joins a host of ANY_PEER
+	rollback: Rollback,
+	remote_ack: u32,  // local inputs the peer has received
+	is_connected: bool,
+	silent_ticks: u32, // since the last packet of the peer
+	advert: Advert,   // broadcast to the lobby while waiting for a peer to join
+	advert_ticks: u32, // until the next advert
+}
+
+impl NetSession {
+	pub fn new(bind: &str, peer: &str, local: usize, input_delay: u32, arena: Arena) -> io::Result<Self> {
+		let socket = UdpSocket::bind(bind)?;
+		socket.set_nonblocking(true)?;
+		socket.set_broadcast(true)?;
+		let peer = match peer {
+			ANY_PEER => None,
+			peer => Some(peer.to_socket_addrs()?
+				.next()
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "peer address"))?),
+		};
+		match peer {
+			Some(peer) => info!("NETWORK: {} <-> {}", socket.local_addr()?, peer),
+			None       => info!("NETWORK: {} waiting for a peer", socket.local_addr()?),
+		}
+
+		let advert = Advert {
+			port: socket.local_addr()?.port(),
+			name: ADVERT_NAME.into(),
+			rules: format!("versus to {WIN_CONDITIONS}, input delay {input_delay}"),
+			players: 1,
+			peer_side: Some(SIDES[1 - local]),
+		};
+		Ok(Self {
+			socket,
+			peer,
+			rollback: Rollback::new(local, input_delay, arena),
+			remote_ack: 0,
+			is_connected: false,
+			silent_ticks: 0,
+			advert,
+			advert_ticks: 0,
+		})
+	}
+
+	pub fn simulation(&self) -> &Simulation {
+		self.rollback.simulation()
+	}
+
+	pub fn is_connected(&self) -> bool {
+		self.is_connected
+	}
+
+	pub fn local_side(&self) -> Side {
+		SIDES[self.rollback.local]
+	}
+
+	pub fn local_address(&self) -> io::Result<SocketAddr> {
+		self.socket.local_addr()
+	}
+
+	// Diverged games and silent peers end the session, neither peer plays on alone
+	pub fn end(&self) -> Option<SessionEnd> {
+		match self.rollback.desync() {
+			Some(tick) => Some(SessionEnd::Desync(tick)),
+			None       => (self.silent_ticks > PEER_TIMEOUT).then_some(SessionEnd::PeerLeft),
+		}
+	}
+
+	// One fixed tick: exchange inputs and advance the simulation
+	pub fn update(&mut self, input: PaddleInput) -> Option<StepEvents> {
+		if self.end().is_some() { return None }
+
+		self.receive();
+		if self.is_connected {
+			self.silent_ticks += 1;
+			if self.silent_ticks > PEER_TIMEOUT {
+				warn!("NETWORK: peer left");
+				return None;
+			}
+		}
+
+		let events = match self.is_connected {
+			true => {
+				self.rollback.add_local_input(input);
+				self.rollback.advance()
+			}
+			false => None,
+		};
+
+		if let Some(peer) = self.peer {
+			let packet = self.rollback.packet(self.remote_ack);
+			if let Err(error) = self.socket.send_to(&packet.encode(), peer) {
+				warn!("NETWORK: {error}");
+			}
+		}
+		if self.peer.is_none() { self.advertise() }
+
+		events
+	}
+
+	// Hosts of ANY_PEER show up in the lobby of the local network until someone joins
+	fn advertise(&mut self) {
+		if self.advert_ticks > 0 { self.advert_ticks -= 1; return }
+		self.advert_ticks = ADVERT_INTERVAL;
+		if let Err(error) = self.socket.send_to(&self.advert.encode(), (Ipv4Addr::BROADCAST, DISCOVERY_PORT)) {
+			warn!("LOBBY: {error}");
+		}
+	}
+
+	fn receive(&mut self) {
+		let mut buffer = [0; 512];
+		loop {
+			let (size, address) = match self.socket.recv_from(&mut buffer) {
+				Ok(received) => received,
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+				Err(error) => { warn!("NETWORK: {error}"); break }
+			};
+			if self.peer.is_some_and(|peer| peer != address) { continue }
+			let Some(packet) = Packet::decode(&buffer[..size]) else { continue };
+
+			if !self.is_connected { info!("NETWORK: peer {address} connected") }
+			self.peer = Some(address);
+			self.is_connected = true;
+			self.silent_ticks = 0;
+			self.remote_ack = self.remote_ack.max(packet.ack);
+
+			for (tick, input) in (packet.first_tick..).zip(packet.inputs) {
+				self.rollback.add_remote_input(tick, input);
+			}
+			if let Some((tick, checksum)) = packet.checksum {
+				self.rollback.check_remote_checksum(tick, checksum);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TICKS: usize = 600;
+	const LATE: usize = 4;
+
+	// Inputs of both paddles changing often enough to be mispredicted
+	fn inputs(tick: usize) -> [PaddleInput; 2] {
+		[
+			PaddleInput::new(tick % 40 < 20, tick % 50 >= 30, false),
+			PaddleInput::new(tick % 30 < 10, tick % 70 >= 35, false),
+		]
+	}
+
+	fn packet(checksum: Option<(u32, u64)>) -> Packet {
+		Packet {
+			first_tick: 1234,
+			inputs: (0..20).map(|tick| inputs(tick)[0]).collect(),
+			ack: 1230,
+			checksum,
+		}
+	}
+
+	#[test]
+	fn packets_survive_a_round_trip() {
+		for checksum in [None, Some((1200, 0x0123_4567_89ab_cdef))] {
+			let packet = packet(checksum);
+			assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+		}
+	}
+
+	#[test]
+	fn truncated_packets_are_rejected() {
+		let bytes = packet(Some((1200, 42))).encode();
+		for size in 0..bytes.len() {
+			assert_eq!(Packet::decode(&bytes[..size]), None, "decoded {size} of {} bytes", bytes.len());
+		}
+	}
+
+	#[test]
+	fn garbage_is_rejected() {
+		let mut bytes = packet(None).encode();
+		bytes[0] = b'X';
+		assert_eq!(Packet::decode(&bytes), None);
+
+		// more inputs announced than sent
+		let mut bytes = packet(None).encode();
+		bytes[5] = u8::MAX;
+		assert_eq!(Packet::decode(&bytes), None);
+
+		// input with bits no button sets
+		let mut bytes = packet(None).encode();
+		bytes[6] = u8::MAX;
+		assert_eq!(Packet::decode(&bytes), None);
+	}
+
+	#[test]
+	fn late_remote_inputs_resimulate_to_the_straight_run() {
+		let arena = Arena::default();
+		let mut rollback = Rollback::new(0, 0, arena.clone());
+		for tick in 0..TICKS {
+			// remote inputs arrive LATE ticks late, the ones still missing all come with the last tick
+			let arrived = if tick + 1 == TICKS { TICKS } else { (tick + 1).saturating_sub(LATE) };
+			for remote_tick in 0..arrived {
+				rollback.add_remote_input(remote_tick as u32, inputs(remote_tick)[1]);
+			}
+			rollback.add_local_input(inputs(tick)[0]);
+			assert!(rollback.advance().is_some(), "stalled at tick {tick}");
+		}
+
+		let mut simulation = Simulation::default();
+		for tick in 0..TICKS {
+			simulation.step(inputs(tick), &arena);
+		}
+		assert_eq!(rollback.simulation(), &simulation);
+	}
+
+	#[test]
+	fn peers_agree_on_checksums() {
+		let arena = Arena::default();
+		let mut peers = [Rollback::new(0, 2, arena.clone()), Rollback::new(1, 2, arena)];
+		let mut acks = [0; 2];
+		for tick in 0..TICKS {
+			for (index, peer) in peers.iter_mut().enumerate() {
+				peer.add_local_input(inputs(tick)[index]);
+				peer.advance();
+			}
+
+			let packets = [0, 1].map(|index| peers[index].packet(acks[index]).encode());
+			for (index, bytes) in packets.iter().enumerate() {
+				let packet = Packet::decode(bytes).unwrap();
+				let receiver = &mut peers[1 - index];
+				acks[1 - index] = acks[1 - index].max(packet.ack);
+				for (tick, input) in (packet.first_tick..).zip(packet.inputs) {
+					receiver.add_remote_input(tick, input);
+				}
+				if let Some((tick, checksum)) = packet.checksum {
+					receiver.check_remote_checksum(tick, checksum);
+				}
+			}
+		}
+
+		assert!(peers.iter().all(|peer| peer.latest_checksum().is_some()));
+		assert!(peers.iter().all(|peer| peer.desync.is_none()));
+	}
+
+	#[test]
+	fn checksum_mismatch_is_a_desync() {
+		let mut rollback = Rollback::new(0, 0, Arena::default());
+		for tick in 0..10 {
+			rollback.add_remote_input(tick, PaddleInput::default());
+			rollback.add_local_input(PaddleInput::default());
+			rollback.advance();
+		}
+
+		let (tick, checksum) = rollback.latest_checksum().unwrap();
+		rollback.check_remote_checksum(tick, checksum);
+		assert_eq!(rollback.desync, None);
+
+		rollback.check_remote_checksum(tick, checksum ^ 1);
+		assert_eq!(rollback.desync, Some(tick));
+	}
+	#[test]
+	fn silent_peers_end_the_session() {
+		let mut host = NetSession::new("127.0.0.1:0", ANY_PEER, 0, 0, Arena::default()).unwrap();
+		let address = host.local_address().unwrap().to_string();
+		let mut guest = NetSession::new("127.0.0.1:0", &address, 1, 0, Arena::default()).unwrap();
+
+		for _ in 0..10 {
+			guest.update(PaddleInput::default());
+			std::thread::sleep(std::time::Duration::from_millis(1));
+			host.update(PaddleInput::default());
+		}
+		assert!(host.is_connected());
+		assert_eq!(host.end(), None);
+
+		// the guest stops sending
+		for _ in 0..=PEER_TIMEOUT {
+			host.update(PaddleInput::default());
+		}
+		assert_eq!(host.end(), Some(SessionEnd::PeerLeft));
+		let tick = host.simulation().tick;
+		assert!(host.update(PaddleInput::default()).is_none());
+		assert_eq!(host.simulation().tick, tick);
+	}
+}
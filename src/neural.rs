@@ -0,0 +1,172 @@
+// Small feed-forward network steering a paddle, weights are evolved offline by pong-trainer
+// and stored as RON. Inputs are the default observation of the training environment,
+// the single output is the direction of the paddle.
+
+// import std
+use std::fs;
+use std::path::Path;
+
+// import custom
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// import crate
+use crate::env::ObservationConfig;
+
+// Networks shipped with the game, by skill
+const BUILTIN_NETWORKS: [(&str, &str); 3] = [
+	("easy",   include_str!("../assets/ai/easy.ron")),
+	("medium", include_str!("../assets/ai/medium.ron")),
+	("hard",   include_str!("../assets/ai/hard.ron")),
+];
+
+// Fully connected, tanh after every layer; weights are row-major, one row per output
+#[derive(Serialize, Deserialize, Debug, Clone)] pub struct Layer {
+	pub inputs: usize,
+	pub outputs: usize,
+	pub weights: Vec<f32>,
+	pub biases: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)] pub struct NeuralNetwork {
+	pub layers: Vec<Layer>,
+}
+
+impl Layer {
+	fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+		self.weights.chunks_exact(self.inputs)
+			.zip(&self.biases)
+			.map(|(row, bias)| (row.iter().zip(inputs).map(|(weight, input)| weight * input).sum::<f32>() + bias).tanh())
+			.collect()
+	}
+}
+
+impl NeuralNetwork {
+	// Layer sizes from the observation to the single output, e.g. [7, 8, 1]
+	pub fn random(sizes: &[usize], rng: &mut impl Rng) -> Self {
+		let layers = sizes.windows(2)
+			.map(|pair| Layer {
+				inputs: pair[0],
+				outputs: pair[1],
+				weights: (0..pair[0] * pair[1]).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+				biases: (0..pair[1]).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+			})
+			.collect();
+		Self { layers }
+	}
+
+	// Name of a built-in network or path to a *.ron file
+	pub fn load(spec: &str) -> Result<Self, String> {
+		let text = match BUILTIN_NETWORKS.iter().find(|(name, _)| *name == spec) {
+			Some((_, text)) => text.to_string(),
+			None => fs::read_to_string(spec).map_err(|error| error.to_string())?,
+		};
+		let network: Self = ron::from_str(&text).map_err(|error| error.to_string())?;
+		network.validate()?;
+		Ok(network)
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+		let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|error| error.to_string())?;
+		fs::write(path, text).map_err(|error| error.to_string())
+	}
+
+	fn validate(&self) -> Result<(), String> {
+		let mut inputs = ObservationConfig::default().observation_size();
+		for layer in &self.layers {
+			if layer.inputs == 0 || layer.outputs == 0 {
+				return Err("layer without inputs or outputs".into());
+			}
+			if layer.inputs != inputs || layer.weights.len() != layer.inputs * layer.outputs || layer.biases.len() != layer.outputs {
+				return Err(format!("layer of {} inputs and {} outputs doesn't fit", layer.inputs, layer.outputs));
+			}
+			inputs = layer.outputs;
+		}
+		match inputs {
+			1 => Ok(()),
+			_ => Err("network needs a single output".into()),
+		}
+	}
+
+	pub fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+		self.layers.iter().fold(inputs.to_vec(), |values, layer| layer.forward(&values))
+	}
+
+	// Direction of the paddle within -1..1
+	pub fn direction(&self, observation: &[f32]) -> f32 {
+		self.forward(observation).first().copied().unwrap_or(0.0)
+	}
+
+	// Every weight and bias, the genome of the trainer
+	pub fn parameters(&self) -> impl Iterator<Item = &f32> {
+		self.layers.iter().flat_map(|layer| layer.weights.iter().chain(&layer.biases))
+	}
+
+	pub fn parameters_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+		self.layers.iter_mut().flat_map(|layer| layer.weights.iter_mut().chain(&mut layer.biases))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+
+	fn layer(inputs: usize, outputs: usize) -> Layer {
+		Layer { inputs, outputs, weights: vec![0.5; inputs * outputs], biases: vec![0.0; outputs] }
+	}
+
+	#[test]
+	fn builtin_networks_load() {
+		for (name, _) in BUILTIN_NETWORKS {
+			let network = NeuralNetwork::load(name).unwrap();
+			let direction = network.direction(&vec![0.0; ObservationConfig::default().observation_size()]);
+			assert!((-1.0..=1.0).contains(&direction), "{name} steers {direction}");
+		}
+		assert!(NeuralNetwork::load("no/such/network.ron").is_err());
+	}
+
+	#[test]
+	fn saved_networks_load_the_same() {
+		let network = NeuralNetwork::random(&[ObservationConfig::default().observation_size(), 4, 1], &mut StdRng::seed_from_u64(7));
+		let path = std::env::temp_dir().join(format!("pong-network-{}.ron", std::process::id()));
+		network.save(&path).unwrap();
+		let loaded = NeuralNetwork::load(path.to_str().unwrap());
+		let _ = fs::remove_file(&path);
+
+		assert_eq!(loaded.unwrap().parameters().collect::<Vec<_>>(), network.parameters().collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn broken_networks_are_rejected() {
+		let inputs = ObservationConfig::default().observation_size();
+		let broken = [
+			vec![],
+			vec![layer(inputs, 0), layer(0, 1)],
+			vec![layer(inputs, 2)],
+			vec![layer(inputs + 1, 1)],
+			vec![Layer { biases: vec![], ..layer(inputs, 1) }],
+			vec![Layer { weights: vec![0.5; inputs], ..layer(inputs, 2) }, layer(2, 1)],
+		];
+		for layers in broken {
+			assert!(NeuralNetwork { layers: layers.clone() }.validate().is_err(), "accepted {layers:?}");
+		}
+		assert_eq!(NeuralNetwork { layers: vec![layer(inputs, 3), layer(3, 1)] }.validate(), Ok(()));
+	}
+
+	#[test]
+	fn forward_applies_weights_biases_and_tanh() {
+		let network = NeuralNetwork { layers: vec![
+			Layer { inputs: 2, outputs: 2, weights: vec![1.0, 2.0, -1.0, 0.0], biases: vec![0.0, 0.5] },
+			Layer { inputs: 2, outputs: 1, weights: vec![1.0, 1.0], biases: vec![-0.25] },
+		] };
+		let hidden = [(1.0f32 * 0.5 + 2.0 * 0.25).tanh(), (-0.5f32 + 0.5).tanh()];
+		let expected = (hidden[0] + hidden[1] - 0.25).tanh();
+
+		let output = network.forward(&[0.5, 0.25]);
+		assert_eq!(output.len(), 1);
+		assert!((output[0] - expected).abs() < 1e-6, "{output:?} instead of {expected}");
+		assert_eq!(network.direction(&[0.5, 0.25]), output[0]);
+	}
+}
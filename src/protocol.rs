@@ -0,0 +1,532 @@
+// Protocol of the dedicated server.
+// Clients send their input every tick, the server answers with snapshots of the authoritative
+// state which clients render slightly in the past, interpolating between two of them.
+// Servers also broadcast adverts on the local network so clients can find them.
+
+// import std
+use std::collections::VecDeque;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+// import bevy
+use bevy::prelude::*;
+
+// import custom
+use socket2::{Domain, Protocol, Socket, Type};
+
+// import crate
+use crate::Side;
+use crate::simulation::{PaddleInput, Phase, Simulation, SIDES, TICK_SECONDS};
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "0.0.0.0:7878";
+pub const DISCOVERY_PORT: u16          = 7879;
+// Seconds without packets after which the other end is considered gone
+pub const CONNECTION_TIMEOUT: f32 = 3.0;
+// Seconds the rendered state lags behind the newest snapshot
+const INTERPOLATION_DELAY: f32 = 0.1;
+const SNAPSHOT_HISTORY: usize  = 32;
+
+const INPUT_MAGIC: u8    = b'I';
+const WATCH_MAGIC: u8    = b'W';
+const SNAPSHOT_MAGIC: u8 = b'S';
+const ADVERT_MAGIC: u8   = b'L';
+const NO_SIDE: u8        = u8::MAX;
+
+// Sent by clients every tick
+#[derive(Debug, Clone, Copy, PartialEq)] pub struct InputPacket {
+	pub sequence: u32, // packets older than the last one received are dropped
+	pub input: PaddleInput,
+}
+
+// Sent by spectators every tick instead of inputs
+#[derive(Debug, Clone, Copy, PartialEq)] pub struct WatchPacket;
+
+// Sent by the server to every client every tick
+#[derive(Debug, Clone, PartialEq)] pub struct Snapshot {
+	pub tick: u32,           // counted by the server, keeps going while the match waits
+	pub players: u8,         // connected players, the match runs with two
+	pub side: Option<Side>,  // paddle of the recipient
+	pub phase: Phase,
+	pub ball_position: Vec2,
+	pub paddle_positions: [f32; 2],
+	pub conceded: [u32; 2],
+	pub collision: bool,     // the ball bounced during this tick
+}
+
+// Broadcast by servers and waiting peer to peer hosts about once a second
+#[derive(Debug, Clone, PartialEq)] pub struct Advert {
+	pub port: u16, // of the server, the address is the sender of the advert
+	pub name: String,
+	pub rules: String,
+	pub players: u8,
+	pub peer_side: Option<Side>, // paddle left to the joining player of a peer to peer host, None for servers
+}
+
+// Reads fixed size fields from the front of a packet
+struct Fields<'a>(&'a [u8]);
+impl<'a> Fields<'a> {
+	fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+		let (field, rest) = self.0.split_first_chunk::<N>()?;
+		self.0 = rest;
+		Some(*field)
+	}
+
+	fn u8(&mut self) -> Option<u8>   { self.take::<1>().map(|[byte]| byte) }
+	fn u32(&mut self) -> Option<u32> { self.take().map(u32::from_le_bytes) }
+	fn f32(&mut self) -> Option<f32> { self.take().map(f32::from_le_bytes).filter(|value| value.is_finite()) }
+	fn u16(&mut self) -> Option<u16> { self.take().map(u16::from_le_bytes) }
+
+	// Packets with bytes left over are malformed
+	fn end(&self) -> Option<()> {
+		self.0.is_empty().then_some(())
+	}
+
+	// Paddle of the versus match or none, None if malformed
+	fn side(&mut self) -> Option<Option<Side>> {
+		match self.u8()? {
+			NO_SIDE => Some(None),
+			index   => SIDES.get(usize::from(index)).copied().map(Some),
+		}
+	}
+
+	// Length prefixed text
+	fn text(&mut self) -> Option<String> {
+		let length = usize::from(self.u8()?);
+		let (text, rest) = (self.0.get(..length)?, self.0.get(length..)?);
+		self.0 = rest;
+		Some(String::from_utf8_lossy(text).into_owned())
+	}
+}
+
+fn push_side(bytes: &mut Vec<u8>, side: Option<Side>) {
+	let index = side.and_then(|side| SIDES.iter().position(|paddle_side| *paddle_side == side));
+	bytes.push(index.map_or(NO_SIDE, |index| index as u8));
+}
+
+fn push_text(bytes: &mut Vec<u8>, text: &str) {
+	let mut length = text.len().min(usize::from(u8::MAX));
+	while !text.is_char_boundary(length) { length -= 1; }
+	bytes.push(length as u8);
+	bytes.extend(&text.as_bytes()[..length]);
+}
+
+impl InputPacket {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = vec![INPUT_MAGIC];
+		bytes.extend(self.sequence.to_le_bytes());
+		bytes.push(self.input.0);
+		bytes
+	}
+
+	pub fn decode(bytes: &[u8]) -> Option<Self> {
+		let mut fields = Fields(bytes);
+		if fields.u8()? != INPUT_MAGIC { return None }
+
+		let packet = Self {
+			sequence: fields.u32()?,
+			input: PaddleInput::from_bits(fields.u8()?)?,
+		};
+		fields.end()?;
+		Some(packet)
+	}
+}
+
+impl WatchPacket {
+	pub fn encode(&self) -> Vec<u8> {
+		vec![WATCH_MAGIC]
+	}
+
+	pub fn decode(bytes: &[u8]) -> Option<Self> {
+		(bytes == [WATCH_MAGIC]).then_some(Self)
+	}
+}
+
+impl Advert {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = vec![ADVERT_MAGIC];
+		bytes.extend(self.port.to_le_bytes());
+		bytes.push(self.players);
+		push_side(&mut bytes, self.peer_side);
+		push_text(&mut bytes, &self.name);
+		push_text(&mut bytes, &self.rules);
+		bytes
+	}
+
+	pub fn decode(bytes: &[u8]) -> Option<Self> {
+		let mut fields = Fields(bytes);
+		if fields.u8()? != ADVERT_MAGIC { return None }
+
+		let advert = Self {
+			port: fields.u16()?,
+			players: fields.u8()?,
+			peer_side: fields.side()?,
+			name: fields.text()?,
+			rules: fields.text()?,
+		};
+		fields.end()?;
+		Some(advert)
+	}
+}
+
+impl Snapshot {
+	pub fn new(tick: u32, simulation: &Simulation, players: u8, side: Option<Side>, collision: bool) -> Self {
+		Self {
+			tick,
+			players,
+			side,
+			phase: simulation.phase,
+			ball_position: simulation.ball_position,
+			paddle_positions: simulation.paddle_positions,
+			conceded: simulation.conceded,
+			collision,
+		}
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let (phase, counter) = self.phase.to_parts();
+		let mut bytes = vec![SNAPSHOT_MAGIC];
+		bytes.extend(self.tick.to_le_bytes());
+		bytes.push(self.players);
+		push_side(&mut bytes, self.side);
+		bytes.push(phase);
+		bytes.extend(counter.to_le_bytes());
+		bytes.extend(self.ball_position.x.to_le_bytes());
+		bytes.extend(self.ball_position.y.to_le_bytes());
+		bytes.extend(self.paddle_positions.iter().flat_map(|position| position.to_le_bytes()));
+		bytes.extend(self.conceded.iter().flat_map(|conceded| conceded.to_le_bytes()));
+		bytes.push(u8::from(self.collision));
+		bytes
+	}
+
+	pub fn decode(bytes: &[u8]) -> Option<Self> {
+		let mut fields = Fields(bytes);
+		if fields.u8()? != SNAPSHOT_MAGIC { return None }
+
+		let tick = fields.u32()?;
+		let players = fields.u8()?;
+		let side = fields.side()?;
+		let phase = fields.u8()?;
+		let snapshot = Self {
+			tick,
+			players,
+			side,
+			phase: Phase::from_parts(phase, fields.u32()?)?,
+			ball_position: Vec2::new(fields.f32()?, fields.f32()?),
+			paddle_positions: [fields.f32()?, fields.f32()?],
+			conceded: [fields.u32()?, fields.u32()?],
+			collision: match fields.u8()? {
+				0 => false,
+				1 => true,
+				_ => return None,
+			},
+		};
+		fields.end()?;
+		Some(snapshot)
+	}
+
+	// State between two snapshots, jumps instead when a set starts or ends between them
+	fn lerp(&self, next: &Snapshot, fraction: f32) -> Snapshot {
+		if self.phase != next.phase { return self.clone() }
+
+		let mut snapshot = self.clone();
+		snapshot.ball_position = self.ball_position.lerp(next.ball_position, fraction);
+		for (position, next_position) in snapshot.paddle_positions.iter_mut().zip(next.paddle_positions) {
+			*position += (next_position - *position) * fraction;
+		}
+		snapshot
+	}
+}
+
+impl Default for Snapshot {
+	fn default() -> Self {
+		Self::new(0, &Simulation::default(), 0, None, false)
+	}
+}
+
+// Client end of the connection to a dedicated server
+#[derive(Resource)] pub struct ServerConnection {
+	socket: UdpSocket,
+	server: SocketAddr,
+	is_spectator: bool, // watches the match without a paddle
+	sequence: u32,
+	snapshots: VecDeque<Snapshot>,  // ordered by tick
+	clock_offset: Option<f32>,      // server time minus local time
+	last_received: f32,
+}
+
+impl ServerConnection {
+	pub fn new(server: &str, is_spectator: bool) -> io::Result<Self> {
+		let server = server.to_socket_addrs()?
+			.next()
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "server address"))?;
+		let bind = match server {
+			SocketAddr::V4(_) => "0.0.0.0:0",
+			SocketAddr::V6(_) => "[::]:0",
+		};
+		let socket = UdpSocket::bind(bind)?;
+		socket.set_nonblocking(true)?;
+		info!("SERVER: connecting to {server}");
+
+		Ok(Self {
+			socket,
+			server,
+			is_spectator,
+			sequence: 0,
+			snapshots: VecDeque::new(),
+			clock_offset: None,
+			last_received: 0.0,
+		})
+	}
+
+	pub fn is_connected(&self, now: f32) -> bool {
+		!self.snapshots.is_empty() && now - self.last_received < CONNECTION_TIMEOUT
+	}
+
+	// Send the input (ignored for spectators), receive snapshots; returns whether the ball bounced since the last call
+	pub fn update(&mut self, input: PaddleInput, now: f32) -> bool {
+		self.sequence += 1;
+		let packet = match self.is_spectator {
+			true  => WatchPacket.encode(),
+			false => InputPacket { sequence: self.sequence, input }.encode(),
+		};
+		if let Err(error) = self.socket.send_to(&packet, self.server) {
+			warn!("SERVER: {error}");
+		}
+
+		let mut collision = false;
+		let mut buffer = [0; 512];
+		loop {
+			let (size, address) = match self.socket.recv_from(&mut buffer) {
+				Ok(received) => received,
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+				Err(error) => { warn!("SERVER: {error}"); break }
+			};
+			if address != self.server { continue }
+			let Some(snapshot) = Snapshot::decode(&buffer[..size]) else { continue };
+
+			// A restarted server counts ticks from zero again
+			if self.snapshots.back().is_some_and(|newest| newest.tick > snapshot.tick + SNAPSHOT_HISTORY as u32) {
+				self.snapshots.clear();
+				self.clock_offset = None;
+			}
+			if self.snapshots.back().is_some_and(|newest| newest.tick >= snapshot.tick) { continue }
+
+			// Follow the fastest arrivals, drift slowly when snapshots come late
+			let offset = snapshot.tick as f32 * TICK_SECONDS - now;
+			self.clock_offset = Some(self.clock_offset.map_or(offset, |old| old + (offset - old) * 0.1).max(offset));
+
+			collision |= snapshot.collision;
+			self.last_received = now;
+			self.snapshots.push_back(snapshot);
+			if self.snapshots.len() > SNAPSHOT_HISTORY { self.snapshots.pop_front(); }
+		}
+
+		collision
+	}
+
+	// Server state as it was a moment ago, interpolated between received snapshots
+	pub fn interpolated(&self, now: f32) -> Option<Snapshot> {
+		let render_time = now + self.clock_offset? - INTERPOLATION_DELAY;
+		let render_tick = render_time / TICK_SECONDS;
+
+		let next = self.snapshots.iter().position(|snapshot| snapshot.tick as f32 > render_tick);
+		match next {
+			Some(0) => self.snapshots.front().cloned(),
+			Some(index) => {
+				let (previous, next) = (&self.snapshots[index - 1], &self.snapshots[index]);
+				let fraction = (render_tick - previous.tick as f32) / (next.tick - previous.tick) as f32;
+				Some(previous.lerp(next, fraction))
+			}
+			None => self.snapshots.back().cloned(),
+		}
+	}
+}
+
+// Matches advertised on the local network
+#[derive(Resource)] pub struct LobbyBrowser {
+	socket: UdpSocket,
+	pub entries: Vec<LobbyEntry>, // ordered by address
+	pub selected: usize,
+}
+
+#[derive(Debug, Clone)] pub struct LobbyEntry {
+	pub address: SocketAddr,
+	pub advert: Advert,
+	last_seen: f32,
+}
+
+impl LobbyBrowser {
+	pub fn new() -> io::Result<Self> {
+		// shared with other browsers on the same computer, broadcasts reach all of them
+		let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+		socket.set_reuse_address(true)?;
+		socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)).into())?;
+		let socket = UdpSocket::from(socket);
+		socket.set_nonblocking(true)?;
+
+		Ok(Self { socket, entries: Vec::new(), selected: 0 })
+	}
+
+	pub fn selected(&self) -> Option<&LobbyEntry> {
+		self.entries.get(self.selected)
+	}
+
+	// Collect adverts, forget servers that went quiet
+	pub fn update(&mut self, now: f32) {
+		let mut buffer = [0; 1024];
+		loop {
+			let (size, address) = match self.socket.recv_from(&mut buffer) {
+				Ok(received) => received,
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+				Err(error) => { warn!("LOBBY: {error}"); break }
+			};
+			let Some(advert) = Advert::decode(&buffer[..size]) else { continue };
+			let address = SocketAddr::new(address.ip(), advert.port);
+
+			match self.entries.iter_mut().find(|entry| entry.address == address) {
+				Some(entry) => { entry.advert = advert; entry.last_seen = now; }
+				None => {
+					self.entries.push(LobbyEntry { address, advert, last_seen: now });
+					self.entries.sort_by_key(|entry| entry.address);
+				}
+			}
+		}
+
+		self.entries.retain(|entry| now - entry.last_seen < CONNECTION_TIMEOUT);
+		self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn snapshot(tick: u32, phase: Phase, ball_x: f32) -> Snapshot {
+		Snapshot {
+			tick,
+			players: 2,
+			side: Some(Side::Right),
+			phase,
+			ball_position: Vec2::new(ball_x, -40.0),
+			paddle_positions: [ball_x / 2.0, -ball_x],
+			conceded: [1, 2],
+			collision: true,
+		}
+	}
+
+	fn advert() -> Advert {
+		Advert { port: 7878, name: "pong".into(), rules: "first to 3".into(), players: 1, peer_side: None }
+	}
+
+	// Every packet cut short of its full length
+	fn prefixes(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+		(0..bytes.len()).map(|size| &bytes[..size])
+	}
+
+	#[test]
+	fn packets_survive_a_round_trip() {
+		let input = InputPacket { sequence: 77, input: PaddleInput::new(true, false, true) };
+		assert_eq!(InputPacket::decode(&input.encode()), Some(input));
+		assert_eq!(WatchPacket::decode(&WatchPacket.encode()), Some(WatchPacket));
+		for peer_side in [None, Some(Side::Right)] {
+			let advert = Advert { peer_side, ..advert() };
+			assert_eq!(Advert::decode(&advert.encode()), Some(advert));
+		}
+
+		let phases = [Phase::Start(12), Phase::Active, Phase::NextSet(3), Phase::GameOver];
+		for (phase, side) in phases.into_iter().zip([None, Some(Side::Left), Some(Side::Right), None]) {
+			let snapshot = Snapshot { side, ..snapshot(900, phase, 120.5) };
+			assert_eq!(Snapshot::decode(&snapshot.encode()), Some(snapshot));
+		}
+	}
+
+	#[test]
+	fn long_advert_texts_are_cut_between_characters() {
+		let advert = Advert { name: "é".repeat(200), ..advert() };
+		let decoded = Advert::decode(&advert.encode()).unwrap();
+		assert_eq!(decoded.name, "é".repeat(127));
+		assert_eq!(decoded.rules, advert.rules);
+	}
+
+	#[test]
+	fn short_packets_are_rejected() {
+		let input = InputPacket { sequence: 1, input: PaddleInput::default() }.encode();
+		assert!(prefixes(&input).all(|bytes| InputPacket::decode(bytes).is_none()));
+		assert!(prefixes(&advert().encode()).all(|bytes| Advert::decode(bytes).is_none()));
+		assert!(prefixes(&snapshot(1, Phase::Active, 0.0).encode()).all(|bytes| Snapshot::decode(bytes).is_none()));
+		assert_eq!(WatchPacket::decode(&[]), None);
+	}
+
+	#[test]
+	fn malformed_packets_are_rejected() {
+		// unknown buttons
+		let mut bytes = InputPacket { sequence: 1, input: PaddleInput::default() }.encode();
+		bytes[5] = 1 << 7;
+		assert_eq!(InputPacket::decode(&bytes), None);
+
+		// bytes left over
+		for mut bytes in [
+			InputPacket { sequence: 1, input: PaddleInput::default() }.encode(),
+			WatchPacket.encode(),
+			advert().encode(),
+			snapshot(1, Phase::Active, 0.0).encode(),
+		] {
+			bytes.push(0);
+			assert!(InputPacket::decode(&bytes).is_none() && WatchPacket::decode(&bytes).is_none());
+			assert!(Advert::decode(&bytes).is_none() && Snapshot::decode(&bytes).is_none());
+		}
+
+		// another kind of packet
+		assert_eq!(Snapshot::decode(&advert().encode()), None);
+		assert_eq!(InputPacket::decode(&WatchPacket.encode()), None);
+	}
+
+	#[test]
+	fn malformed_snapshots_are_rejected() {
+		let bytes = snapshot(1, Phase::Active, 0.0).encode();
+		let with = |index: usize, byte: u8| {
+			let mut bytes = bytes.clone();
+			bytes[index] = byte;
+			Snapshot::decode(&bytes)
+		};
+
+		assert_eq!(Phase::from_parts(4, 0), None);
+		assert_eq!(with(7, 4), None);               // unknown phase kind
+		assert_eq!(with(6, 2), None);               // unknown side
+		assert_eq!(with(bytes.len() - 1, 2), None); // collision is a flag
+		assert!(with(6, NO_SIDE).is_some_and(|snapshot| snapshot.side.is_none()));
+
+		// positions that aren't numbers
+		let mut bytes = bytes.clone();
+		bytes[12..16].copy_from_slice(&f32::NAN.to_le_bytes());
+		assert_eq!(Snapshot::decode(&bytes), None);
+	}
+
+	#[test]
+	fn connection_interpolates_between_snapshots() {
+		let mut connection = ServerConnection::new("127.0.0.1:7878", true).unwrap();
+		connection.clock_offset = Some(0.0);
+		connection.snapshots.extend([snapshot(10, Phase::Active, 0.0), snapshot(12, Phase::Active, 20.0)]);
+		let at_tick = |tick: f32| tick * TICK_SECONDS + INTERPOLATION_DELAY;
+
+		let halfway = connection.interpolated(at_tick(11.0)).unwrap();
+		assert!((halfway.ball_position.x - 10.0).abs() < 1e-3, "ball at {}", halfway.ball_position);
+		assert!((halfway.paddle_positions[0] - 5.0).abs() < 1e-3);
+		assert!((halfway.paddle_positions[1] + 10.0).abs() < 1e-3);
+
+		// before the first and after the last snapshot the nearest one is shown
+		assert_eq!(connection.interpolated(at_tick(5.0)), connection.snapshots.front().cloned());
+		assert_eq!(connection.interpolated(at_tick(20.0)), connection.snapshots.back().cloned());
+
+		// a set starting or ending between two snapshots isn't blended
+		connection.snapshots[1].phase = Phase::NextSet(60);
+		assert_eq!(connection.interpolated(at_tick(11.0)), connection.snapshots.front().cloned());
+	}
+
+	#[test]
+	fn browsers_share_the_discovery_port() {
+		let first = LobbyBrowser::new();
+		let second = LobbyBrowser::new();
+		assert!(first.is_ok() && second.is_ok(), "{:?}", second.err());
+	}
+}
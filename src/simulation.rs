@@ -0,0 +1,477 @@
+// Deterministic fixed tick simulation of a versus match.
+// Used where the game has to be stepped and rewound tick by tick, e.g. networked play.
+
+// import std
+use std::time::Duration;
+
+// import bevy
+use bevy::prelude::*;
+use bevy::math::bounding::Aabb2d;
+
+// import custom
+use rand::Rng;
+
+// import crate
+use crate::{
+	ai_target, bounce_ball, speed_up, Arena, BallCollider, GameMode, PaddleMotion, Scoreboard, Side,
+	BALL_DELTA_SPEED, BALL_STARTING_SPEED, NEXT_SET_DELAY, PADDLE_SIZE,
+	PLAYER_MAX_SPEED, SIN_OF_45, START_DELAY,
+};
+
+pub const TICK_RATE: f64 = 60.0;
+pub const TICK_SECONDS: f32 = 1.0 / TICK_RATE as f32;
+
+// Paddles of a versus match, indexes of the simulation arrays
+pub const SIDES: [Side; 2] = [Side::Left, Side::Right];
+
+// Random serves leave within this many radians around the diagonals
+const SERVE_SPREAD: f32 = 0.35;
+
+pub fn ticks(duration: Duration) -> u32 {
+	(duration.as_secs_f32() / TICK_SECONDS).round() as u32
+}
+
+// Buttons held by a player during one tick
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Hash)] pub struct PaddleInput(pub u8);
+impl PaddleInput {
+	pub const UP: u8     = 1 << 0;
+	pub const DOWN: u8   = 1 << 1;
+	pub const ACCEPT: u8 = 1 << 2;
+
+	pub fn new(is_up: bool, is_down: bool, is_accept: bool) -> Self {
+		let mut bits = 0;
+		if is_up     { bits |= Self::UP }
+		if is_down   { bits |= Self::DOWN }
+		if is_accept { bits |= Self::ACCEPT }
+		Self(bits)
+	}
+
+	// Analog moves of bots and learned AIs, buttons are held past a third of the way
+	pub fn from_direction(direction: f32) -> Self {
+		Self::new(direction > 1.0 / 3.0, direction < -1.0 / 3.0, false)
+	}
+
+	// Buttons read from a packet, None if bits no button uses are set
+	pub fn from_bits(bits: u8) -> Option<Self> {
+		(bits & !(Self::UP | Self::DOWN | Self::ACCEPT) == 0).then_some(Self(bits))
+	}
+
+	pub fn direction(self) -> f32 {
+		f32::from(self.0 & Self::UP != 0) - f32::from(self.0 & Self::DOWN != 0)
+	}
+
+	pub fn is_accept(self) -> bool {
+		self.0 & Self::ACCEPT != 0
+	}
+}
+
+// Mirrors GameplayState, counters are ticks left before the ball is served
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)] pub enum Phase {
+	Start(u32),
+	Active,
+	NextSet(u32),
+	GameOver,
+}
+
+impl Phase {
+	// Kind and counter, for checksums and packets
+	pub fn to_parts(self) -> (u8, u32) {
+		match self {
+			Phase::Start(left)   => (0, left),
+			Phase::Active        => (1, 0),
+			Phase::NextSet(left) => (2, left),
+			Phase::GameOver      => (3, 0),
+		}
+	}
+
+	pub fn from_parts(kind: u8, counter: u32) -> Option<Self> {
+		match kind {
+			0 => Some(Phase::Start(counter)),
+			1 => Some(Phase::Active),
+			2 => Some(Phase::NextSet(counter)),
+			3 => Some(Phase::GameOver),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy)] pub struct StepEvents {
+	pub collision: bool,
+	pub hit: Option<usize>, // paddle that touched the ball
+	pub goal: Option<Side>,
+}
+
+#[derive(Debug, Clone, PartialEq)] pub struct Simulation {
+	pub tick: u32, // ticks simulated so far
+	pub phase: Phase,
+	pub ball_position: Vec2,
+	pub ball_velocity: Vec2,
+	pub ball_max_speed: f32,
+	pub serve_direction: Vec2, // of the ball at the start of every set
+	pub paddle_positions: [f32; 2],  // along the side axis
+	pub paddle_velocities: [f32; 2],
+	pub conceded: [u32; 2],
+}
+
+// Built-in AI of headless matches, planning like ai_control of the game
+#[derive(Debug, Default, Clone, Copy)] pub struct HeadlessAi {
+	pub reaction_delay: u32, // ticks between looks at the ball
+	pub aim_error: f32,      // largest offset of the aim from the target
+	pub is_offensive: bool,  // aims returns away from the opponent
+	reaction: u32,           // ticks left before the next look
+	target: Option<f32>,
+	error: f32,
+	was_approaching: bool,
+}
+
+impl Default for Simulation {
+	fn default() -> Self {
+		Self {
+			tick: 0,
+			phase: Phase::Start(ticks(START_DELAY)),
+			ball_position: Vec2::ZERO,
+			ball_velocity: Vec2::ZERO,
+			ball_max_speed: BALL_STARTING_SPEED,
+			serve_direction: Vec2::new(SIN_OF_45, SIN_OF_45),
+			paddle_positions: [0.0; 2],
+			paddle_velocities: [0.0; 2],
+			conceded: [0; 2],
+		}
+	}
+}
+
+impl Simulation {
+	pub fn paddle_translation(&self, index: usize, arena: &Arena) -> Vec2 {
+		let side = SIDES[index];
+		side.paddle_position(arena) + side.axis() * self.paddle_positions[index]
+	}
+
+	// Buttons bringing the paddle to the target, released early enough to stop there
+	fn input_towards(&self, index: usize, target: f32) -> PaddleInput {
+		let velocity = self.paddle_velocities[index];
+		let stopping_distance = velocity * velocity.abs() / (2.0 * PaddleMotion::default().deceleration);
+		let offset = target - self.paddle_positions[index] - stopping_distance;
+		let dead_zone = PADDLE_SIZE.y / 8.0;
+		PaddleInput::new(offset > dead_zone, offset < -dead_zone, false)
+	}
+
+	// Random serve for the next set of headless matches, served at once when delays are skipped
+	pub fn prepare_serve(&mut self, rng: &mut impl Rng, skip_delay: bool) {
+		let (Phase::Start(_) | Phase::NextSet(_)) = self.phase else { return };
+
+		let diagonal = std::f32::consts::FRAC_PI_4 + std::f32::consts::FRAC_PI_2 * rng.gen_range(0..4) as f32;
+		self.serve_direction = Vec2::from_angle(diagonal + rng.gen_range(-SERVE_SPREAD..SERVE_SPREAD));
+
+		if skip_delay {
+			self.phase = match self.phase {
+				Phase::Start(_) => Phase::Start(0),
+				_               => Phase::NextSet(0),
+			};
+		}
+	}
+
+	pub fn step(&mut self, inputs: [PaddleInput; 2], arena: &Arena) -> StepEvents {
+		self.tick += 1;
+
+		// Paddles move in every phase, same as move_paddles
+		for (index, input) in inputs.iter().enumerate() {
+			let velocity = &mut self.paddle_velocities[index];
+			*velocity = PaddleMotion::default().step(*velocity, input.direction(), PLAYER_MAX_SPEED, TICK_SECONDS);
+
+			let bound = GameMode::Versus.paddle_bound(SIDES[index], arena);
+			let position = self.paddle_positions[index] + *velocity * TICK_SECONDS;
+			self.paddle_positions[index] = position.clamp(-bound, bound);
+			if self.paddle_positions[index] != position { *velocity = 0.0 }
+		}
+
+		match self.phase {
+			Phase::Start(0) | Phase::NextSet(0) => {
+				self.phase = Phase::Active;
+				self.ball_velocity = self.serve_direction * BALL_STARTING_SPEED;
+			}
+			Phase::Start(left)   => self.phase = Phase::Start(left - 1),
+			Phase::NextSet(left) => self.phase = Phase::NextSet(left - 1),
+			Phase::GameOver => {
+				if inputs.iter().any(|input| input.is_accept()) {
+					self.conceded = [0; 2];
+					self.phase = Phase::Start(ticks(START_DELAY));
+				}
+			}
+			Phase::Active => return self.step_ball(arena),
+		}
+
+		StepEvents::default()
+	}
+
+	fn step_ball(&mut self, arena: &Arena) -> StepEvents {
+		let mut events = StepEvents::default();
+
+		self.ball_velocity = self.ball_velocity.clamp_length_max(self.ball_max_speed);
+		self.ball_position += self.ball_velocity * TICK_SECONDS;
+
+		// collide with walls and paddles as check_ball_collisions does
+		let colliders = [0, 1].map(|index| BallCollider {
+			aabb: Aabb2d::new(self.paddle_translation(index, arena), PADDLE_SIZE / 2.0),
+			paddle: Some(SIDES[index]),
+		});
+		let bounce = bounce_ball(self.ball_position, self.ball_velocity, &colliders, arena);
+		let mut scoreboard = self.scoreboard();
+		events.goal = scoreboard.goal(bounce.walls, self.ball_position, arena);
+		events.hit = bounce.hits.last().map(|(index, _)| *index);
+		events.collision = bounce.is_collision();
+
+		self.ball_position = bounce.position;
+		self.ball_velocity = bounce.velocity;
+		if events.collision {
+			(self.ball_velocity, self.ball_max_speed) = speed_up(self.ball_velocity, self.ball_max_speed, BALL_DELTA_SPEED);
+		}
+
+		// process scoreboard
+		if let Some(side) = events.goal {
+			scoreboard.concede(side);
+			self.conceded = SIDES.map(|side| scoreboard.conceded[side as usize]);
+
+			self.ball_position = Vec2::ZERO;
+			self.ball_velocity = Vec2::ZERO;
+			self.ball_max_speed = BALL_STARTING_SPEED;
+			self.phase = match scoreboard.is_decided() {
+				true  => Phase::GameOver,
+				false => Phase::NextSet(ticks(NEXT_SET_DELAY)),
+			};
+		}
+
+		events
+	}
+
+	// The game's scoreboard of a versus match, goals and the end of the match follow its rules
+	fn scoreboard(&self) -> Scoreboard {
+		let mut conceded = [0; 4];
+		for (index, side) in SIDES.into_iter().enumerate() {
+			conceded[side as usize] = self.conceded[index];
+		}
+		Scoreboard { conceded, in_play: Side::ALL.map(|side| SIDES.contains(&side)) }
+	}
+
+	// FNV-1a over the whole state, compared between peers to detect desyncs
+	pub fn checksum(&self) -> u64 {
+		let (phase, counter) = self.phase.to_parts();
+		let words = [
+			self.tick,
+			u32::from(phase),
+			counter,
+			self.ball_position.x.to_bits(),
+			self.ball_position.y.to_bits(),
+			self.ball_velocity.x.to_bits(),
+			self.ball_velocity.y.to_bits(),
+			self.ball_max_speed.to_bits(),
+			self.paddle_positions[0].to_bits(),
+			self.paddle_positions[1].to_bits(),
+			self.paddle_velocities[0].to_bits(),
+			self.paddle_velocities[1].to_bits(),
+			self.conceded[0],
+			self.conceded[1],
+		];
+
+		words.iter()
+			.flat_map(|word| word.to_le_bytes())
+			.fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+	}
+}
+
+impl HeadlessAi {
+	pub fn new(reaction_delay: Duration, aim_error: f32, is_offensive: bool) -> Self {
+		Self { reaction_delay: ticks(reaction_delay), aim_error, is_offensive, ..default() }
+	}
+
+	// Buttons of the paddle at index for this tick, from the plan of ai_control: a new aim error every time the
+	// ball heads for the paddle, and the ball looked at once per reaction delay
+	pub fn input(&mut self, simulation: &Simulation, index: usize, arena: &Arena, rng: &mut impl Rng) -> PaddleInput {
+		let side = SIDES[index];
+		let ball = (simulation.ball_position, simulation.ball_velocity);
+
+		let is_approaching = ball.1.dot(side.normal()) > 0.0;
+		if is_approaching && !self.was_approaching {
+			self.error = rng.gen_range(-1.0..=1.0) * self.aim_error;
+		}
+		self.was_approaching = is_approaching;
+
+		self.reaction = self.reaction.saturating_sub(1);
+		if self.reaction == 0 || self.target.is_none() {
+			self.reaction = self.reaction_delay;
+			let opponent = self.is_offensive.then_some(simulation.paddle_positions[1 - index]);
+			let target = ai_target(side, simulation.paddle_positions[index], side.paddle_size(), PLAYER_MAX_SPEED, ball, opponent, arena);
+			self.target = Some(target + self.error);
+		}
+
+		simulation.input_towards(index, self.target.unwrap_or_default())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::math::bounding::IntersectsVolume;
+	use crate::{aim_away, BALL_SIZE, WIN_CONDITIONS};
+	use crate::game::state::{Conditions, GameplayState, StateMachine, Trigger};
+	use proptest::prelude::*;
+	use rand::{rngs::StdRng, SeedableRng};
+
+	const EPSILON: f32 = 1e-3;
+	const STEPS: usize = 120;
+
+	fn input() -> impl Strategy<Value = PaddleInput> {
+		(any::<bool>(), any::<bool>()).prop_map(|(is_up, is_down)| PaddleInput::new(is_up, is_down, false))
+	}
+
+	proptest! {
+		// Ball and paddles stay in the arena, the ball never rests inside a paddle and only speeds up on collisions
+		#[test]
+		fn steps_keep_invariants(
+			ball_position in (-300.0f32..300.0, -220.0f32..220.0),
+			ball_angle in 0.0..std::f32::consts::TAU,
+			ball_max_speed in BALL_STARTING_SPEED..1000.0,
+			paddle_positions in (-200.0f32..200.0, -200.0f32..200.0),
+			paddle_velocities in (-PLAYER_MAX_SPEED..PLAYER_MAX_SPEED, -PLAYER_MAX_SPEED..PLAYER_MAX_SPEED),
+			inputs in prop::collection::vec((input(), input()), STEPS),
+		) {
+			let arena = Arena::default();
+			let bound = GameMode::Versus.paddle_bound(Side::Left, &arena);
+			let mut simulation = Simulation {
+				phase: Phase::Active,
+				ball_position: Vec2::new(ball_position.0, ball_position.1),
+				ball_velocity: Vec2::from_angle(ball_angle) * ball_max_speed,
+				ball_max_speed,
+				paddle_positions: [paddle_positions.0.clamp(-bound, bound), paddle_positions.1.clamp(-bound, bound)],
+				paddle_velocities: [paddle_velocities.0, paddle_velocities.1],
+				..Simulation::default()
+			};
+			let paddle = |simulation: &Simulation, index| Aabb2d::new(simulation.paddle_translation(index, &arena), PADDLE_SIZE / 2.0);
+			let ball = Aabb2d::new(simulation.ball_position, BALL_SIZE / 2.0);
+			prop_assume!((0..2).all(|index| !ball.intersects(&paddle(&simulation, index))));
+
+			for (left, right) in inputs {
+				let speed = simulation.ball_velocity.length();
+				let events = simulation.step([left, right], &arena);
+				if simulation.phase != Phase::Active { break }
+
+				let ball = Aabb2d::new(simulation.ball_position, BALL_SIZE / 2.0);
+				prop_assert!(ball.min.x >= arena.left_wall() - EPSILON && ball.max.x <= arena.right_wall() + EPSILON);
+				prop_assert!(ball.min.y >= arena.bottom_wall() - EPSILON && ball.max.y <= arena.top_wall() + EPSILON);
+				for index in 0..2 {
+					let paddle = paddle(&simulation, index);
+					let overlap = ball.max.min(paddle.max) - ball.min.max(paddle.min);
+					prop_assert!(overlap.x <= EPSILON || overlap.y <= EPSILON, "ball inside paddle {index} at {}", simulation.ball_position);
+					prop_assert!(paddle.min.y >= arena.bottom_wall() - EPSILON && paddle.max.y <= arena.top_wall() + EPSILON);
+				}
+
+				// collisions speed the ball up to its new max speed, anything else keeps its speed
+				let new_speed = simulation.ball_velocity.length();
+				match events.collision {
+					true  => prop_assert!(new_speed <= speed.max(simulation.ball_max_speed) + EPSILON * speed),
+					false => prop_assert!(new_speed <= speed + EPSILON * speed),
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn headless_ai_looks_at_the_ball_once_per_reaction_delay() {
+		let arena = Arena::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let mut ai = HeadlessAi::new(Duration::from_millis(100), 0.0, false);
+		let mut simulation = Simulation { phase: Phase::Active, ball_velocity: Vec2::new(300.0, 0.0), ..default() };
+		ai.input(&simulation, 1, &arena, &mut rng);
+		assert_eq!(ai.target, Some(0.0));
+
+		// the ball is followed where it was last seen
+		simulation.ball_position.y = 50.0;
+		for _ in 1..ai.reaction_delay {
+			ai.input(&simulation, 1, &arena, &mut rng);
+			assert_eq!(ai.target, Some(0.0));
+		}
+		ai.input(&simulation, 1, &arena, &mut rng);
+		assert_eq!(ai.target, Some(50.0));
+	}
+
+	#[test]
+	fn offensive_headless_ai_aims_like_the_game() {
+		let arena = Arena::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let mut ai = HeadlessAi::new(Duration::ZERO, 0.0, true);
+		let simulation = Simulation {
+			phase: Phase::Active,
+			ball_velocity: Vec2::new(300.0, 100.0),
+			paddle_positions: [120.0, 0.0],
+			..default()
+		};
+		ai.input(&simulation, 1, &arena, &mut rng);
+
+		let ball = (simulation.ball_position, simulation.ball_velocity);
+		let aim = aim_away(Side::Right, 0.0, Side::Right.paddle_size(), PLAYER_MAX_SPEED, ball, 120.0, &arena);
+		assert!(aim.is_some());
+		assert_eq!(ai.target, aim);
+	}
+
+	fn gameplay_state(phase: Phase) -> GameplayState {
+		match phase {
+			Phase::Start(_)   => GameplayState::Start,
+			Phase::Active     => GameplayState::Active,
+			Phase::NextSet(_) => GameplayState::NextSet,
+			Phase::GameOver   => GameplayState::GameOver,
+		}
+	}
+
+	#[test]
+	fn phases_switch_like_the_state_machine_of_the_game() {
+		use GameplayState::*;
+
+		let arena = Arena::default();
+		let machine = StateMachine::<GameplayState>::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let mut simulation = Simulation::default();
+		let mut switches = Vec::new();
+
+		// idle paddles concede until the match is over, then it is started again
+		while simulation.tick < 60 * 60 * 10 && !switches.contains(&(GameOver, Start)) {
+			simulation.prepare_serve(&mut rng, true);
+			let from = simulation.phase;
+			simulation.step([PaddleInput::new(false, false, from == Phase::GameOver); 2], &arena);
+			let to = simulation.phase;
+			if from.to_parts().0 == to.to_parts().0 { continue }
+
+			let trigger = match from {
+				Phase::Active   => Trigger::SetOver,
+				Phase::GameOver => Trigger::Accept,
+				_               => Trigger::Timeout,
+			};
+			let conditions = Conditions { is_browsing: false, is_decided: simulation.scoreboard().is_decided() };
+			assert_eq!(machine.next(&gameplay_state(from), trigger, &conditions), Some(&gameplay_state(to)), "{from:?} to {to:?}");
+			switches.push((gameplay_state(from), gameplay_state(to)));
+		}
+		for switch in [(Start, Active), (Active, NextSet), (NextSet, Active), (Active, GameOver), (GameOver, Start)] {
+			assert!(switches.contains(&switch), "{switch:?} never happened");
+		}
+	}
+
+	#[test]
+	fn match_ends_like_the_game_scoreboard() {
+		let arena = Arena::default();
+		let bound = GameMode::Versus.paddle_bound(Side::Left, &arena);
+		for (conceded, phase) in [(1, Phase::NextSet(ticks(NEXT_SET_DELAY))), (WIN_CONDITIONS - 1, Phase::GameOver)] {
+			// ball past the left paddle, which stands out of the way
+			let mut simulation = Simulation {
+				phase: Phase::Active,
+				ball_position: Vec2::new(arena.left_wall() + BALL_SIZE.x, 0.0),
+				ball_velocity: Vec2::NEG_X * BALL_STARTING_SPEED,
+				paddle_positions: [bound, 0.0],
+				conceded: [conceded, 0],
+				..Simulation::default()
+			};
+
+			let events = simulation.step([PaddleInput::default(); 2], &arena);
+			assert_eq!(events.goal, Some(Side::Left));
+			assert_eq!(simulation.conceded, [conceded + 1, 0]);
+			assert_eq!(simulation.phase, phase);
+		}
+	}
+}